@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::release_notes::ReleaseCommit;
+
+pub const CACHE_DIR: &str = "ginger-releaser";
+const CACHE_FILE: &str = "commits.json";
+
+/// Parsed commits from earlier runs, keyed by commit id, so that only new commits have to be
+/// parsed or diffed. The cache is thrown away when `key`, which covers the settings that change how
+/// commits are parsed, differs from the one it was written with.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct CommitCache {
+    key: String,
+    commits: HashMap<String, ReleaseCommit>,
+    /// Whether each commit changes the paths the changelog is limited to
+    #[serde(default)]
+    touches: HashMap<String, bool>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    #[serde(skip)]
+    changed: bool,
+}
+
+impl CommitCache {
+    /// Reads the cache from `dir`. A missing or unreadable cache simply starts out empty.
+    pub fn load(dir: &Path, key: &str) -> CommitCache {
+        let path = dir.join(CACHE_FILE);
+        let cache = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<CommitCache>(&contents).ok())
+            .filter(|cache| cache.key == key);
+
+        CommitCache {
+            path: Some(path),
+            ..cache.unwrap_or(CommitCache {
+                key: key.to_string(),
+                ..CommitCache::default()
+            })
+        }
+    }
+
+    pub fn get(&self, commit_id: &str) -> Option<&ReleaseCommit> {
+        self.commits.get(commit_id)
+    }
+
+    pub fn insert(&mut self, commit: ReleaseCommit) {
+        self.commits.insert(commit.hash.clone(), commit);
+        self.changed = true;
+    }
+
+    pub fn touches(&self, commit_id: &str) -> Option<bool> {
+        self.touches.get(commit_id).copied()
+    }
+
+    pub fn insert_touches(&mut self, commit_id: &str, touches: bool) {
+        self.touches.insert(commit_id.to_string(), touches);
+        self.changed = true;
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let path = match (&self.path, self.changed) {
+            (Some(path), true) => path,
+            _ => return Ok(()),
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+            // A `cache_dir` inside the work tree must not end up in the release commit
+            let gitignore = dir.join(".gitignore");
+            if !gitignore.exists() {
+                fs::write(gitignore, "*\n")?;
+            }
+        }
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,189 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+static RE_LINK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\[[^\]]+\]: \S+").unwrap());
+
+/// A release section that is already present in CHANGELOG.md, kept verbatim.
+#[derive(Debug, Clone)]
+pub struct ExistingRelease {
+    pub version: String,
+    pub text: String,
+}
+
+/// CHANGELOG.md split into whatever comes before the first release heading, the release
+/// sections themselves and the trailing link reference definitions, so new releases can be
+/// inserted without touching the rest.
+#[derive(Debug, Clone, Default)]
+pub struct ChangelogFile {
+    pub preamble: String,
+    pub releases: Vec<ExistingRelease>,
+    pub footer: String,
+}
+
+impl ChangelogFile {
+    /// Splits the file on the lines matched by `re_heading`, which captures the `version`.
+    pub fn parse(contents: &str, re_heading: &Regex) -> ChangelogFile {
+        let headings: Vec<(usize, String)> = re_heading
+            .captures_iter(contents)
+            .map(|caps| (caps.get(0).unwrap().start(), caps["version"].to_string()))
+            .collect();
+
+        let preamble_end = headings
+            .first()
+            .map(|(start, _)| *start)
+            .unwrap_or(contents.len());
+
+        let mut releases = vec![];
+        for (i, (start, version)) in headings.iter().enumerate() {
+            let end = headings
+                .get(i + 1)
+                .map(|(next_start, _)| *next_start)
+                .unwrap_or(contents.len());
+            releases.push(ExistingRelease {
+                version: version.clone(),
+                text: contents[*start..end].to_string(),
+            });
+        }
+
+        // Link reference definitions at the very end belong to the file, not the last release
+        let mut footer = String::new();
+        if let Some(last) = releases.last_mut() {
+            let mut lines: Vec<&str> = last.text.split_inclusive('\n').collect();
+            let mut footer_start = lines.len();
+            while footer_start > 0 {
+                let line = lines[footer_start - 1];
+                if RE_LINK.is_match(line) || line.trim().is_empty() {
+                    footer_start -= 1;
+                } else {
+                    break;
+                }
+            }
+            // Blank lines in front of the definitions stay with the release
+            while footer_start < lines.len() && lines[footer_start].trim().is_empty() {
+                footer_start += 1;
+            }
+            if footer_start < lines.len() {
+                footer = lines.split_off(footer_start).concat();
+                last.text = lines.concat();
+            }
+        }
+
+        ChangelogFile {
+            preamble: contents[..preamble_end].to_string(),
+            releases,
+            footer,
+        }
+    }
+
+    /// True when no release heading was recognized although the file has second level
+    /// headings, usually because the version heading template changed since it was written.
+    pub fn has_unrecognized_headings(&self) -> bool {
+        self.releases.is_empty() && self.preamble.lines().any(|line| line.starts_with("## "))
+    }
+
+    pub fn has_release(&self, version: &str) -> bool {
+        self.releases
+            .iter()
+            .any(|release| release.version == version)
+    }
+
+    pub fn remove_release(&mut self, version: &str) {
+        self.releases.retain(|release| release.version != version);
+    }
+
+    /// Puts a freshly rendered release section above all the existing ones.
+    pub fn insert_release(&mut self, version: &str, text: String) {
+        self.releases.insert(
+            0,
+            ExistingRelease {
+                version: version.to_string(),
+                text,
+            },
+        );
+    }
+
+    pub fn render(&self) -> String {
+        let mut contents = self.preamble.clone();
+        for release in self.releases.iter() {
+            contents.push_str(&release.text);
+        }
+        contents.push_str(&self.footer);
+        contents
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template::{heading_regex, DEFAULT_VERSION_HEADING};
+
+    const CHANGELOG: &str = "# Changelog
+
+## 1.1.0 - 2024-02-01
+
+### Features
+
+ - [abc1234](url) (A) feat: add thing
+
+## 1.0.0 - 2024-01-01
+
+ - [def5678](url) (A) fix: crash
+
+[1.1.0]: https://example.com/compare/1.0.0...1.1.0
+[1.0.0]: https://example.com/tree/1.0.0
+";
+
+    #[test]
+    fn splits_preamble_releases_and_footer() {
+        let changelog = ChangelogFile::parse(CHANGELOG, &heading_regex(DEFAULT_VERSION_HEADING));
+        assert_eq!(changelog.preamble, "# Changelog\n\n");
+        let versions: Vec<&str> = changelog
+            .releases
+            .iter()
+            .map(|release| release.version.as_str())
+            .collect();
+        assert_eq!(versions, vec!["1.1.0", "1.0.0"]);
+        assert!(changelog.releases[0]
+            .text
+            .starts_with("## 1.1.0 - 2024-02-01\n"));
+        assert!(changelog.releases[0].text.ends_with("feat: add thing\n\n"));
+        assert_eq!(
+            changelog.releases[1].text,
+            "## 1.0.0 - 2024-01-01\n\n - [def5678](url) (A) fix: crash\n\n"
+        );
+        assert!(changelog.footer.starts_with("[1.1.0]: "));
+        assert!(!changelog.has_unrecognized_headings());
+        assert_eq!(changelog.render(), CHANGELOG);
+    }
+
+    #[test]
+    fn inserts_releases_on_top() {
+        let mut changelog =
+            ChangelogFile::parse(CHANGELOG, &heading_regex(DEFAULT_VERSION_HEADING));
+        changelog.insert_release("1.2.0", String::from("## 1.2.0 - 2024-03-01\n\n"));
+        changelog.remove_release("1.0.0");
+        assert!(changelog.has_release("1.2.0"));
+        assert!(!changelog.has_release("1.0.0"));
+        assert_eq!(
+            changelog.render(),
+            CHANGELOG
+                .replace("## 1.1.0", "## 1.2.0 - 2024-03-01\n\n## 1.1.0")
+                .replace(
+                    "## 1.0.0 - 2024-01-01\n\n - [def5678](url) (A) fix: crash\n\n",
+                    ""
+                )
+        );
+    }
+
+    #[test]
+    fn notices_headings_of_another_template() {
+        let changelog = ChangelogFile::parse(CHANGELOG, &heading_regex("## v{version}"));
+        assert!(changelog.releases.is_empty());
+        assert_eq!(changelog.preamble, CHANGELOG);
+        assert!(changelog.has_unrecognized_headings());
+
+        let changelog = ChangelogFile::parse("# Changelog\n", &heading_regex("## v{version}"));
+        assert!(!changelog.has_unrecognized_headings());
+    }
+}
@@ -0,0 +1,272 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+static RE_HEADER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?P<type>[A-Za-z][A-Za-z0-9_-]*)(?:\((?P<scope>[^()\r\n]*)\))?(?P<breaking>!)?: \s*(?P<description>\S.*)$",
+    )
+    .unwrap()
+});
+static RE_TRAILER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?P<token>BREAKING CHANGE|[A-Za-z][A-Za-z0-9-]*)(?:: (?P<value>.*)| (?P<issue>#.*))$",
+    )
+    .unwrap()
+});
+static RE_REVERTS: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^This reverts commit (?P<hash>[0-9a-fA-F]{7,40})").unwrap());
+
+/// A commit message broken down according to the Conventional Commits spec.
+/// Messages that do not follow the spec still parse, with `commit_type` left as `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConventionalCommit {
+    pub commit_type: Option<String>,
+    pub scope: Option<String>,
+    pub breaking: bool,
+    pub description: String,
+    pub body: Option<String>,
+    pub trailers: Vec<(String, String)>,
+    /// Hash of the commit this one reverts, from the `This reverts commit <sha>` line git writes
+    pub reverts: Option<String>,
+}
+
+impl ConventionalCommit {
+    pub fn parse(message: &str) -> ConventionalCommit {
+        let message = message.trim();
+        let (header, rest) = match message.split_once('\n') {
+            Some((header, rest)) => (header.trim(), rest),
+            None => (message, ""),
+        };
+
+        let (commit_type, scope, breaking, description) = match RE_HEADER.captures(header) {
+            Some(caps) => (
+                Some(caps["type"].to_lowercase()),
+                caps.name("scope")
+                    .map(|scope| scope.as_str().trim().to_string())
+                    .filter(|scope| !scope.is_empty()),
+                caps.name("breaking").is_some(),
+                caps["description"].trim().to_string(),
+            ),
+            None => (None, None, false, header.to_string()),
+        };
+
+        let mut paragraphs: Vec<String> = rest
+            .split("\n\n")
+            .map(|paragraph| paragraph.trim_matches('\n').to_string())
+            .filter(|paragraph| !paragraph.trim().is_empty())
+            .collect();
+
+        // The trailing paragraphs made only of trailers are the footers. `git commit -s` adds
+        // `Signed-off-by` in a paragraph of its own after a `BREAKING CHANGE:` footer.
+        let mut trailers: Vec<(String, String)> = vec![];
+        while let Some(mut footers) = paragraphs.last().and_then(|last| parse_trailers(last)) {
+            paragraphs.pop();
+            footers.append(&mut trailers);
+            trailers = footers;
+        }
+
+        let body = if paragraphs.is_empty() {
+            None
+        } else {
+            Some(paragraphs.join("\n\n"))
+        };
+
+        let breaking = breaking
+            || trailers
+                .iter()
+                .any(|(token, _)| is_breaking_change_token(token));
+
+        let reverts = RE_REVERTS
+            .captures(rest)
+            .map(|caps| caps["hash"].to_lowercase());
+
+        ConventionalCommit {
+            commit_type,
+            scope,
+            breaking,
+            description,
+            body,
+            trailers,
+            reverts,
+        }
+    }
+
+    /// Explanation given in a `BREAKING CHANGE:` / `BREAKING-CHANGE:` footer, if any.
+    pub fn breaking_change(&self) -> Option<&str> {
+        self.trailers
+            .iter()
+            .find(|(token, _)| is_breaking_change_token(token))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn is_breaking_change_token(token: &str) -> bool {
+    token == "BREAKING CHANGE" || token == "BREAKING-CHANGE"
+}
+
+fn parse_trailers(paragraph: &str) -> Option<Vec<(String, String)>> {
+    let mut trailers: Vec<(String, String)> = vec![];
+    for line in paragraph.lines() {
+        match RE_TRAILER.captures(line) {
+            Some(caps) => {
+                let value = caps.name("value").or(caps.name("issue")).unwrap();
+                trailers.push((caps["token"].to_string(), value.as_str().to_string()));
+            }
+            None => match trailers.last_mut() {
+                // As in git, only indented lines continue the previous trailer, except for the
+                // breaking change explanation, which the spec lets span several lines. Any other
+                // line makes the whole paragraph part of the body.
+                Some((token, value))
+                    if line.starts_with([' ', '\t']) || is_breaking_change_token(token) =>
+                {
+                    value.push('\n');
+                    value.push_str(line.trim());
+                }
+                _ => return None,
+            },
+        }
+    }
+
+    Some(trailers)
+}
+
+/// Human readable section title for a normalized commit type.
+pub fn section_title(commit_type: Option<&str>) -> String {
+    match commit_type {
+        Some("feat") => String::from("Features"),
+        Some("fix") => String::from("Bug Fixes"),
+        Some("perf") => String::from("Performance Improvements"),
+        Some("refactor") => String::from("Code Refactoring"),
+        Some("revert") => String::from("Reverted"),
+        Some("docs") => String::from("Documentation"),
+        Some("style") => String::from("Styles"),
+        Some("test") => String::from("Tests"),
+        Some("build") => String::from("Build System"),
+        Some("ci") => String::from("Continuous Integration"),
+        Some("chore") => String::from("Chores"),
+        Some(other) => {
+            let mut chars = other.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::from("Other"),
+            }
+        }
+        None => String::from("Other"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_headers() {
+        let cases = [
+            ("feat: add thing", Some("feat"), None, false, "add thing"),
+            (
+                "Fix(API)!: drop v1",
+                Some("fix"),
+                Some("API"),
+                true,
+                "drop v1",
+            ),
+            (
+                "feat(): empty scope",
+                Some("feat"),
+                None,
+                false,
+                "empty scope",
+            ),
+            ("feat:no space", None, None, false, "feat:no space"),
+            (
+                "https://example.com is down",
+                None,
+                None,
+                false,
+                "https://example.com is down",
+            ),
+            ("random message", None, None, false, "random message"),
+        ];
+        for (message, commit_type, scope, breaking, description) in cases {
+            let parsed = ConventionalCommit::parse(message);
+            assert_eq!(parsed.commit_type.as_deref(), commit_type, "{}", message);
+            assert_eq!(parsed.scope.as_deref(), scope, "{}", message);
+            assert_eq!(parsed.breaking, breaking, "{}", message);
+            assert_eq!(parsed.description, description, "{}", message);
+        }
+    }
+
+    #[test]
+    fn parses_bodies_and_footers() {
+        let cases = [
+            (
+                "fix: crash\n\nNote: the cache is now rebuilt on start.\nThis takes longer.",
+                Some("Note: the cache is now rebuilt on start.\nThis takes longer."),
+                vec![],
+            ),
+            (
+                "fix: crash\n\nFirst paragraph.\n\nSecond paragraph.\n\nCloses #12",
+                Some("First paragraph.\n\nSecond paragraph."),
+                vec![("Closes", "#12")],
+            ),
+            (
+                "feat: drop v1\n\nBREAKING CHANGE: the v1 API is gone\n\nSigned-off-by: A <a@b.c>",
+                None,
+                vec![
+                    ("BREAKING CHANGE", "the v1 API is gone"),
+                    ("Signed-off-by", "A <a@b.c>"),
+                ],
+            ),
+            (
+                "feat: drop v1\n\nBREAKING-CHANGE: the v1 API is gone\nuse v2 instead",
+                None,
+                vec![("BREAKING-CHANGE", "the v1 API is gone\nuse v2 instead")],
+            ),
+            (
+                "feat: thing\n\nReviewed-by: A\n  and B\nRefs: #1",
+                None,
+                vec![("Reviewed-by", "A\nand B"), ("Refs", "#1")],
+            ),
+            (
+                "feat: thing\n\nbody\n\nRefs: #1\nnot a trailer",
+                Some("body\n\nRefs: #1\nnot a trailer"),
+                vec![],
+            ),
+        ];
+        for (message, body, trailers) in cases {
+            let parsed = ConventionalCommit::parse(message);
+            let expected: Vec<(String, String)> = trailers
+                .iter()
+                .map(|(token, value)| (token.to_string(), value.to_string()))
+                .collect();
+            assert_eq!(parsed.body.as_deref(), body, "{}", message);
+            assert_eq!(parsed.trailers, expected, "{}", message);
+        }
+    }
+
+    #[test]
+    fn finds_breaking_changes() {
+        let parsed = ConventionalCommit::parse(
+            "feat: drop v1\n\nBREAKING CHANGE: the v1 API is gone\n\nSigned-off-by: A <a@b.c>",
+        );
+        assert!(parsed.breaking);
+        assert_eq!(parsed.breaking_change(), Some("the v1 API is gone"));
+
+        let parsed = ConventionalCommit::parse("feat!: drop v1");
+        assert!(parsed.breaking);
+        assert_eq!(parsed.breaking_change(), None);
+
+        let parsed = ConventionalCommit::parse("feat: add v2\n\nThe v1 API still works.");
+        assert!(!parsed.breaking);
+    }
+
+    #[test]
+    fn finds_reverted_commits() {
+        let parsed = ConventionalCommit::parse(
+            "Revert \"feat: add thing\"\n\nThis reverts commit 1A2B3C4D5E6F.",
+        );
+        assert_eq!(parsed.commit_type, None);
+        assert_eq!(parsed.reverts.as_deref(), Some("1a2b3c4d5e6f"));
+    }
+}
@@ -0,0 +1,216 @@
+use std::error::Error;
+use std::fmt;
+
+use git2::Repository;
+use inquire::{
+    autocompletion::Replacement, validator::Validation, Autocomplete, Confirm, CustomUserError,
+    Editor, Select, Text,
+};
+
+use crate::commit::section_title;
+use crate::config::ChangelogConfig;
+use crate::lint::lint_message;
+use crate::utils::description_render_config;
+
+const NO_SCOPE: &str = "(none)";
+
+struct TypeOption {
+    commit_type: String,
+    title: String,
+}
+
+impl fmt::Display for TypeOption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:<10} {}", self.commit_type, self.title)
+    }
+}
+
+/// Shows how much of the first line the subject takes up while it is typed. Inquire only
+/// calls back on input through the autocompleter, so the counter is its single suggestion.
+#[derive(Clone)]
+struct LengthCounter {
+    taken: usize,
+    limit: usize,
+}
+
+impl LengthCounter {
+    fn count(&self, subject: &str) -> String {
+        format!(
+            "{}/{} characters",
+            self.taken + subject.chars().count(),
+            self.limit
+        )
+    }
+
+    fn is_count(&self, text: &str) -> bool {
+        text.strip_suffix(&format!("/{} characters", self.limit))
+            .is_some_and(|count| count.parse::<usize>().is_ok())
+    }
+}
+
+impl Autocomplete for LengthCounter {
+    fn get_suggestions(&mut self, input: &str) -> Result<Vec<String>, CustomUserError> {
+        Ok(vec![self.count(input)])
+    }
+
+    fn get_completion(
+        &mut self,
+        _input: &str,
+        _highlighted_suggestion: Option<String>,
+    ) -> Result<Replacement, CustomUserError> {
+        Ok(None)
+    }
+}
+
+/// Asks for the parts of a conventional commit message and commits the staged changes with it.
+pub fn compose_commit(changelog_config: &ChangelogConfig) -> Result<(), Box<dyn Error>> {
+    let repo = Repository::discover(".")?;
+    let mut index = repo.index()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let parent = match repo.head() {
+        Ok(head) => Some(head.peel_to_commit()?),
+        // No commit yet on a new branch
+        Err(_) => None,
+    };
+    let unchanged = match &parent {
+        Some(parent) => parent.tree_id() == tree.id(),
+        None => index.is_empty(),
+    };
+    if unchanged {
+        return Err("Nothing to commit, stage your changes first".into());
+    }
+
+    let type_options: Vec<TypeOption> = changelog_config
+        .allowed_types()
+        .into_iter()
+        .map(|commit_type| TypeOption {
+            title: changelog_config
+                .sections
+                .iter()
+                .find(|section| section.commit_type == commit_type)
+                .map(|section| section.title.clone())
+                .unwrap_or(section_title(Some(&commit_type))),
+            commit_type,
+        })
+        .collect();
+    let commit_type = Select::new("Type:", type_options).prompt()?.commit_type;
+
+    let scopes = &changelog_config.lint.scopes;
+    let scope = if scopes.is_empty() {
+        Text::new("Scope:")
+            .with_help_message("Optional, the part of the project that changed")
+            .prompt()?
+    } else {
+        let mut options = vec![String::from(NO_SCOPE)];
+        options.extend(scopes.iter().cloned());
+        Select::new("Scope:", options).prompt()?
+    };
+    let scope = scope.trim();
+    let scope = if scope.is_empty() || scope == NO_SCOPE {
+        String::new()
+    } else {
+        format!("({})", scope)
+    };
+
+    let breaking = Confirm::new("Does this break compatibility?")
+        .with_default(false)
+        .prompt()?;
+    let prefix = format!(
+        "{}{}{}: ",
+        commit_type,
+        scope,
+        if breaking { "!" } else { "" }
+    );
+
+    let counter = LengthCounter {
+        taken: prefix.chars().count(),
+        limit: changelog_config.lint.max_header_length,
+    };
+    let subject = Text::new("Subject:")
+        .with_autocomplete(counter.clone())
+        .with_validator(move |subject: &str| {
+            // Picking the counter with the arrow keys would submit it as the subject
+            if subject.trim().is_empty() || counter.is_count(subject) {
+                return Ok(Validation::Invalid("The subject can not be empty".into()));
+            }
+            if counter.taken + subject.chars().count() > counter.limit {
+                return Ok(Validation::Invalid(
+                    format!(
+                        "The first line can be at most {} characters long",
+                        counter.limit
+                    )
+                    .into(),
+                ));
+            }
+            Ok(Validation::Valid)
+        })
+        .prompt()?;
+
+    let body = Editor::new("Body:")
+        .with_formatter(&|submission| {
+            if submission.trim().is_empty() {
+                String::from("<skipped>")
+            } else {
+                String::from("<added>")
+            }
+        })
+        .with_render_config(description_render_config())
+        .prompt()?;
+
+    let breaking_change = if breaking {
+        Text::new("Describe the breaking change:")
+            .with_validator(|text: &str| {
+                if text.trim().is_empty() {
+                    return Ok(Validation::Invalid(
+                        "Tell users what they have to change".into(),
+                    ));
+                }
+                Ok(Validation::Valid)
+            })
+            .prompt()?
+    } else {
+        String::new()
+    };
+
+    let issues = Text::new("Issue references:")
+        .with_help_message("Optional, comma separated, e.g. #12, #34")
+        .prompt()?;
+
+    let mut message = format!("{}{}", prefix, subject.trim());
+    if !body.trim().is_empty() {
+        message.push_str(&format!("\n\n{}", body.trim()));
+    }
+    let mut footers: Vec<String> = vec![];
+    if !breaking_change.trim().is_empty() {
+        footers.push(format!("BREAKING CHANGE: {}", breaking_change.trim()));
+    }
+    if !issues.trim().is_empty() {
+        footers.push(format!("Refs: {}", issues.trim()));
+    }
+    if !footers.is_empty() {
+        message.push_str(&format!("\n\n{}", footers.join("\n")));
+    }
+
+    let problems = lint_message(&message, changelog_config);
+    if !problems.is_empty() {
+        return Err(problems.join(", ").into());
+    }
+
+    println!("\n{}\n", message);
+    if !Confirm::new("Commit?").with_default(true).prompt()? {
+        return Ok(());
+    }
+
+    let signature = repo.signature()?;
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+    let commit_id = repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        &message,
+        &tree,
+        &parents,
+    )?;
+    println!("Created commit {}", &commit_id.to_string()[..10]);
+    Ok(())
+}
@@ -0,0 +1,311 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+
+use ginger_shared_rs::ReleaserConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::commit::section_title;
+use crate::forge::Forge;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SectionConfig {
+    #[serde(rename = "type")]
+    pub commit_type: String,
+    pub title: String,
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+/// Each template can be given inline or read from a file, relative to the project root.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct TemplatesConfig {
+    pub header: Option<String>,
+    pub header_file: Option<String>,
+    pub version_heading: Option<String>,
+    pub version_heading_file: Option<String>,
+    pub section_heading: Option<String>,
+    pub section_heading_file: Option<String>,
+    pub entry: Option<String>,
+    pub entry_file: Option<String>,
+}
+
+/// An issue tracker: references matching `pattern` link to `url`, in which `{id}`,
+/// `{reference}` and the named groups of the pattern are replaced.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct IssueTrackerConfig {
+    pub pattern: String,
+    pub url: String,
+}
+
+/// Globs, relative to the repository root, limiting the changelog to commits changing the
+/// matching files.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct PathsConfig {
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+/// What `lint-commit` accepts. The types default to the ones of `sections` and any scope is
+/// allowed when `scopes` is empty.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LintConfig {
+    #[serde(default)]
+    pub types: Vec<String>,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    #[serde(default = "default_max_header_length")]
+    pub max_header_length: usize,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        LintConfig {
+            types: vec![],
+            scopes: vec![],
+            max_header_length: default_max_header_length(),
+        }
+    }
+}
+
+fn default_max_header_length() -> usize {
+    72
+}
+
+/// The page written by `changelog --format html`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct HtmlConfig {
+    pub title: Option<String>,
+    /// Stylesheet used instead of the built-in one, relative to the project root
+    pub css_file: Option<String>,
+}
+
+/// Commits left out of the changelog. Commits marked with `[skip changelog]` always are.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ExcludeConfig {
+    /// Regexes matched against the first line of the commit message
+    #[serde(default)]
+    pub subjects: Vec<String>,
+    /// Regexes matched against the author name and email
+    #[serde(default)]
+    pub authors: Vec<String>,
+    #[serde(default)]
+    pub merge_commits: bool,
+    /// The `chore: version bump to X` commits created on release
+    #[serde(default = "default_true")]
+    pub version_bumps: bool,
+}
+
+impl Default for ExcludeConfig {
+    fn default() -> Self {
+        ExcludeConfig {
+            subjects: vec![],
+            authors: vec![],
+            merge_commits: false,
+            version_bumps: true,
+        }
+    }
+}
+
+/// The "Contributors" list of each release.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ContributorsConfig {
+    #[serde(default = "default_true")]
+    pub show: bool,
+    /// Regexes matched against the name and email of bot accounts, which are not listed
+    #[serde(default = "default_bots")]
+    pub bots: Vec<String>,
+}
+
+impl Default for ContributorsConfig {
+    fn default() -> Self {
+        ContributorsConfig {
+            show: true,
+            bots: default_bots(),
+        }
+    }
+}
+
+fn default_bots() -> Vec<String> {
+    vec![
+        String::from(r"\[bot\]"),
+        String::from(r"^github-actions"),
+        String::from(r"^renovate"),
+    ]
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangelogStyle {
+    #[default]
+    Default,
+    /// The layout described on keepachangelog.com
+    KeepAChangelog,
+}
+
+/// What happens to the nightly, alpha and beta releases once the final version ships.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PrereleaseMode {
+    /// Every pre-release keeps its own section
+    #[default]
+    Separate,
+    /// The final release lists their commits and the pre-releases are listed under it
+    Collapse,
+    /// The final release lists their commits and the pre-releases are left out
+    Hide,
+}
+
+/// The `[changelog]` table of releaser.toml. It lives next to the shared `ReleaserConfig`
+/// rather than inside it, so it is read and preserved separately.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChangelogConfig {
+    #[serde(default)]
+    pub style: ChangelogStyle,
+    /// Forge used to build links, guessed from the repository URL when not set
+    pub forge: Option<Forge>,
+    #[serde(default = "default_sections")]
+    pub sections: Vec<SectionConfig>,
+    #[serde(default)]
+    pub templates: TemplatesConfig,
+    /// Keep a Changelog category per commit type, on top of `default_category`
+    #[serde(default)]
+    pub categories: BTreeMap<String, String>,
+    #[serde(default)]
+    pub issues: Vec<IssueTrackerConfig>,
+    #[serde(default)]
+    pub exclude: ExcludeConfig,
+    /// Only walk the first-parent history, with every merge commit as a single entry
+    #[serde(default)]
+    pub first_parent: bool,
+    #[serde(default)]
+    pub contributors: ContributorsConfig,
+    /// Show the commit body under each entry, not only the subject line
+    #[serde(default)]
+    pub include_body: bool,
+    /// Date releases by when their annotated tag was made rather than by their last commit
+    #[serde(default)]
+    pub tagger_date: bool,
+    /// How release tags are named, `{version}` by default. Other tags are ignored.
+    pub tag_format: Option<String>,
+    /// Fills in `{package}` in `tag_format`
+    pub package: Option<String>,
+    /// Where parsed commits are cached, `.git/ginger-releaser` by default
+    pub cache_dir: Option<String>,
+    #[serde(default)]
+    pub html: HtmlConfig,
+    #[serde(default)]
+    pub paths: PathsConfig,
+    #[serde(default)]
+    pub prereleases: PrereleaseMode,
+    #[serde(default)]
+    pub lint: LintConfig,
+}
+
+impl Default for ChangelogConfig {
+    fn default() -> Self {
+        ChangelogConfig {
+            style: ChangelogStyle::default(),
+            forge: None,
+            sections: default_sections(),
+            templates: TemplatesConfig::default(),
+            categories: BTreeMap::new(),
+            issues: vec![],
+            exclude: ExcludeConfig::default(),
+            first_parent: false,
+            contributors: ContributorsConfig::default(),
+            include_body: false,
+            tagger_date: false,
+            tag_format: None,
+            package: None,
+            cache_dir: None,
+            html: HtmlConfig::default(),
+            paths: PathsConfig::default(),
+            prereleases: PrereleaseMode::default(),
+            lint: LintConfig::default(),
+        }
+    }
+}
+
+fn default_sections() -> Vec<SectionConfig> {
+    [
+        "feat", "fix", "perf", "refactor", "revert", "docs", "style", "test", "build", "ci",
+        "chore",
+    ]
+    .iter()
+    .map(|commit_type| SectionConfig {
+        commit_type: commit_type.to_string(),
+        title: section_title(Some(commit_type)),
+        hidden: false,
+    })
+    .collect()
+}
+
+impl ChangelogConfig {
+    /// The commit types `lint-commit` accepts.
+    pub fn allowed_types(&self) -> Vec<String> {
+        if !self.lint.types.is_empty() {
+            return self.lint.types.clone();
+        }
+        self.sections
+            .iter()
+            .map(|section| section.commit_type.clone())
+            .collect()
+    }
+
+    /// The Keep a Changelog category a commit type is listed under.
+    pub fn category(&self, commit_type: Option<&str>) -> String {
+        match commit_type.and_then(|commit_type| self.categories.get(commit_type)) {
+            Some(category) => category.clone(),
+            None => default_category(commit_type).to_string(),
+        }
+    }
+}
+
+fn default_category(commit_type: Option<&str>) -> &'static str {
+    match commit_type {
+        Some("feat") => "Added",
+        Some("fix") => "Fixed",
+        Some("deprecate") | Some("deprecated") => "Deprecated",
+        Some("remove") | Some("removed") | Some("revert") => "Removed",
+        Some("security") | Some("sec") => "Security",
+        _ => "Changed",
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ReleaserFile {
+    #[serde(default)]
+    changelog: ChangelogConfig,
+}
+
+pub fn read_changelog_config(config_path: &str) -> Result<ChangelogConfig, Box<dyn Error>> {
+    let contents = fs::read_to_string(config_path)?;
+    let releaser_file: ReleaserFile = toml::from_str(&contents)?;
+    Ok(releaser_file.changelog)
+}
+
+/// Writes the releaser config back to disk while keeping the tables that the shared
+/// `ReleaserConfig` does not know about, such as `[changelog]`.
+pub fn write_releaser_config(
+    config_path: &str,
+    config: &ReleaserConfig,
+) -> Result<(), Box<dyn Error>> {
+    let existing: toml::Table = toml::from_str(&fs::read_to_string(config_path)?)?;
+    let mut updated = toml::Table::try_from(config)?;
+
+    for (key, value) in existing {
+        if !updated.contains_key(&key) {
+            updated.insert(key, value);
+        }
+    }
+
+    fs::write(config_path, toml::to_string(&updated)?)?;
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::release_notes::ReleaseCommit;
+
+static RE_IDENTITY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(?P<name>.*?)\s*<(?P<email>[^>]*)>$").unwrap());
+
+/// Someone credited in a commit through a `Co-authored-by:` trailer.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CoAuthor {
+    pub name: String,
+    pub email: String,
+}
+
+/// Someone who authored or co-authored a commit of a release.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Contributor {
+    pub name: String,
+    pub email: String,
+    /// Whether none of the earlier releases has a commit by them
+    #[serde(default)]
+    pub new: bool,
+}
+
+/// The co-authors given in `Co-authored-by: Name <email>` trailers.
+pub fn parse_co_authors(trailers: &[(String, String)]) -> Vec<CoAuthor> {
+    trailers
+        .iter()
+        .filter(|(token, _)| token.eq_ignore_ascii_case("Co-authored-by"))
+        .map(|(_, value)| match RE_IDENTITY.captures(value.trim()) {
+            Some(caps) => CoAuthor {
+                name: caps["name"].to_string(),
+                email: caps["email"].to_string(),
+            },
+            None => CoAuthor {
+                name: value.trim().to_string(),
+                email: String::new(),
+            },
+        })
+        .collect()
+}
+
+// People are told apart by email, as names are spelled differently from one machine to another
+fn identity(name: &str, email: &str) -> String {
+    if email.is_empty() {
+        name.to_lowercase()
+    } else {
+        email.to_lowercase()
+    }
+}
+
+/// Everyone who authored or co-authored `commits`, in order of appearance, leaving out the
+/// authors matching one of the `bots` patterns. `known` holds the identities of earlier
+/// contributors and is updated with the new ones.
+pub fn collect_contributors(
+    commits: &[ReleaseCommit],
+    bots: &[Regex],
+    known: &mut HashSet<String>,
+) -> Vec<Contributor> {
+    let mut people: Vec<(&str, &str)> = vec![];
+    for commit in commits {
+        people.push((&commit.author, &commit.email));
+        for co_author in commit.co_authors.iter() {
+            people.push((&co_author.name, &co_author.email));
+        }
+    }
+
+    let mut contributors: Vec<Contributor> = vec![];
+    let mut seen: HashSet<String> = HashSet::new();
+    for (name, email) in people {
+        let is_bot = bots
+            .iter()
+            .any(|re| re.is_match(name) || re.is_match(email));
+        if is_bot || !seen.insert(identity(name, email)) {
+            continue;
+        }
+        contributors.push(Contributor {
+            name: name.to_string(),
+            email: email.to_string(),
+            new: false,
+        });
+    }
+
+    // Nobody is new in the very first release
+    let first_release = known.is_empty();
+    for contributor in contributors.iter_mut() {
+        contributor.new =
+            known.insert(identity(&contributor.name, &contributor.email)) && !first_release;
+    }
+    contributors
+}
+
+/// Identities of everyone who authored or co-authored `commits`.
+pub fn identities(commits: &[ReleaseCommit]) -> HashSet<String> {
+    let mut identities = HashSet::new();
+    for commit in commits {
+        identities.insert(identity(&commit.author, &commit.email));
+        for co_author in commit.co_authors.iter() {
+            identities.insert(identity(&co_author.name, &co_author.email));
+        }
+    }
+    identities
+}
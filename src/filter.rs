@@ -0,0 +1,116 @@
+use std::error::Error;
+
+use git2::{Commit, Repository};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use regex::Regex;
+
+use crate::config::{ExcludeConfig, PathsConfig};
+
+pub const SKIP_MARKER: &str = "[skip changelog]";
+
+// The commit `update_project_source` creates for every release
+pub const VERSION_BUMP_PATTERN: &str = r"^chore: version bump to ";
+
+/// Decides which commits are left out of the release notes.
+#[derive(Debug, Clone)]
+pub struct CommitFilter {
+    re_subjects: Vec<Regex>,
+    re_authors: Vec<Regex>,
+    merge_commits: bool,
+}
+
+impl CommitFilter {
+    pub fn load(config: &ExcludeConfig) -> Result<CommitFilter, Box<dyn Error>> {
+        let mut subjects = config.subjects.clone();
+        if config.version_bumps {
+            subjects.push(String::from(VERSION_BUMP_PATTERN));
+        }
+
+        Ok(CommitFilter {
+            re_subjects: compile(&subjects)?,
+            re_authors: compile(&config.authors)?,
+            merge_commits: config.merge_commits,
+        })
+    }
+
+    pub fn is_excluded(&self, commit: &Commit) -> bool {
+        let message = commit.message().unwrap_or_default();
+        let subject = message.trim().lines().next().unwrap_or_default();
+        let author = commit.author();
+        let author_name = author.name().unwrap_or_default();
+        let author_email = author.email().unwrap_or_default();
+
+        message.to_lowercase().contains(SKIP_MARKER)
+            || (self.merge_commits && commit.parent_count() > 1)
+            || self.re_subjects.iter().any(|re| re.is_match(subject))
+            || self
+                .re_authors
+                .iter()
+                .any(|re| re.is_match(author_name) || re.is_match(author_email))
+    }
+}
+
+/// Keeps the commits that change files within the configured paths, for changelogs that only
+/// cover part of the repository.
+#[derive(Debug, Clone)]
+pub struct PathFilter {
+    include: GlobSet,
+    exclude: GlobSet,
+}
+
+impl PathFilter {
+    pub fn load(config: &PathsConfig) -> Result<PathFilter, Box<dyn Error>> {
+        Ok(PathFilter {
+            include: build_glob_set(&config.include)?,
+            exclude: build_glob_set(&config.exclude)?,
+        })
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.include.is_empty() || !self.exclude.is_empty()
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        (self.include.is_empty() || self.include.is_match(path)) && !self.exclude.is_match(path)
+    }
+
+    /// Whether the commit changes any of the paths, compared to its first parent.
+    pub fn touches(&self, repo: &Repository, commit: &Commit) -> Result<bool, git2::Error> {
+        if !self.is_active() {
+            return Ok(true);
+        }
+
+        let parent_tree = match commit.parents().next() {
+            Some(parent) => Some(parent.tree()?),
+            None => None,
+        };
+        let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+
+        Ok(diff.deltas().any(|delta| {
+            [delta.old_file().path(), delta.new_file().path()]
+                .iter()
+                .flatten()
+                .any(|path| self.matches(&path.to_string_lossy()))
+        }))
+    }
+}
+
+fn build_glob_set(patterns: &[String]) -> Result<GlobSet, Box<dyn Error>> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        match Glob::new(pattern) {
+            Ok(glob) => builder.add(glob),
+            Err(e) => return Err(format!("Invalid path pattern {}: {}", pattern, e).into()),
+        };
+    }
+    Ok(builder.build()?)
+}
+
+pub fn compile(patterns: &[String]) -> Result<Vec<Regex>, Box<dyn Error>> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            Regex::new(pattern).map_err(|e| format!("Invalid pattern {}: {}", pattern, e).into())
+        })
+        .collect()
+}
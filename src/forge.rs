@@ -0,0 +1,188 @@
+use std::sync::LazyLock;
+
+use git2::Repository;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+static RE_URL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?P<scheme>[a-z+]+)://(?:[^@/]+@)?(?P<host>[^/:]+)(?::\d+)?/(?P<path>.+)$")
+        .unwrap()
+});
+static RE_SCP: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(?:[^@/]+@)?(?P<host>[^:/]+):(?P<path>[^/].*)$").unwrap());
+// Prefixes written for the old `prefix + hash` links point at the commit page already
+static RE_COMMIT_SUFFIX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"/(?:-/)?commits?$").unwrap());
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Forge {
+    GitHub,
+    GitLab,
+    /// Gitea and Forgejo share the same URL layout
+    Gitea,
+    Bitbucket,
+}
+
+impl Forge {
+    fn from_host(host: &str) -> Option<Forge> {
+        let host = host.to_lowercase();
+        if host.contains("github") {
+            Some(Forge::GitHub)
+        } else if host.contains("gitlab") {
+            Some(Forge::GitLab)
+        } else if host.contains("bitbucket") {
+            Some(Forge::Bitbucket)
+        } else if host.contains("gitea") || host.contains("forgejo") || host.contains("codeberg") {
+            Some(Forge::Gitea)
+        } else {
+            None
+        }
+    }
+}
+
+/// Builds links to commits, compare views, tags, issues and pull requests of a hosted repository.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForgeLinks {
+    pub forge: Forge,
+    pub base_url: String,
+}
+
+impl ForgeLinks {
+    /// Uses `git_url_prefix` when it is set and falls back to the `origin` remote. The forge is
+    /// guessed from the host unless it is configured, and defaults to GitHub.
+    pub fn resolve(
+        repo: &Repository,
+        git_url_prefix: &str,
+        forge: Option<&Forge>,
+    ) -> Option<ForgeLinks> {
+        let url = if git_url_prefix.trim().is_empty() {
+            let remote = repo.find_remote("origin").ok()?;
+            remote.url()?.to_string()
+        } else {
+            git_url_prefix.to_string()
+        };
+        ForgeLinks::from_url(&url, forge)
+    }
+
+    pub fn from_url(url: &str, forge: Option<&Forge>) -> Option<ForgeLinks> {
+        let base_url = normalize_url(url)?;
+        let host = base_url.split('/').nth(2).unwrap_or_default();
+
+        Some(ForgeLinks {
+            forge: forge
+                .cloned()
+                .or(Forge::from_host(host))
+                .unwrap_or(Forge::GitHub),
+            base_url,
+        })
+    }
+
+    pub fn commit(&self, hash: &str) -> String {
+        match self.forge {
+            Forge::GitHub | Forge::Gitea => format!("{}/commit/{}", self.base_url, hash),
+            Forge::GitLab => format!("{}/-/commit/{}", self.base_url, hash),
+            Forge::Bitbucket => format!("{}/commits/{}", self.base_url, hash),
+        }
+    }
+
+    pub fn compare(&self, from: &str, to: &str) -> String {
+        match self.forge {
+            Forge::GitHub | Forge::Gitea => {
+                format!("{}/compare/{}...{}", self.base_url, from, to)
+            }
+            Forge::GitLab => format!("{}/-/compare/{}...{}", self.base_url, from, to),
+            Forge::Bitbucket => format!("{}/branches/compare/{}%0D{}", self.base_url, to, from),
+        }
+    }
+
+    pub fn tag(&self, tag: &str) -> String {
+        match self.forge {
+            Forge::GitHub | Forge::Gitea => format!("{}/releases/tag/{}", self.base_url, tag),
+            Forge::GitLab => format!("{}/-/tags/{}", self.base_url, tag),
+            Forge::Bitbucket => format!("{}/src/{}", self.base_url, tag),
+        }
+    }
+
+    pub fn issue(&self, id: &str) -> String {
+        match self.forge {
+            Forge::GitHub | Forge::Gitea | Forge::Bitbucket => {
+                format!("{}/issues/{}", self.base_url, id)
+            }
+            Forge::GitLab => format!("{}/-/issues/{}", self.base_url, id),
+        }
+    }
+
+    pub fn pull_request(&self, number: &str) -> String {
+        match self.forge {
+            Forge::GitHub => format!("{}/pull/{}", self.base_url, number),
+            Forge::GitLab => format!("{}/-/merge_requests/{}", self.base_url, number),
+            Forge::Gitea => format!("{}/pulls/{}", self.base_url, number),
+            Forge::Bitbucket => format!("{}/pull-requests/{}", self.base_url, number),
+        }
+    }
+}
+
+/// Turns a remote or configured URL into the https URL of the repository, e.g.
+/// `git@github.com:org/repo.git` into `https://github.com/org/repo`.
+fn normalize_url(url: &str) -> Option<String> {
+    let url = url.trim().trim_end_matches('/');
+    let url = url.strip_suffix(".git").unwrap_or(url);
+
+    let base_url = if let Some(caps) = RE_URL.captures(url) {
+        let scheme = match &caps["scheme"] {
+            "http" => "http",
+            _ => "https",
+        };
+        format!("{}://{}/{}", scheme, &caps["host"], &caps["path"])
+    } else if let Some(caps) = RE_SCP.captures(url) {
+        format!("https://{}/{}", &caps["host"], &caps["path"])
+    } else {
+        return None;
+    };
+
+    Some(RE_COMMIT_SUFFIX.replace(&base_url, "").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_urls() {
+        let cases = [
+            (
+                "git@github.com:org/repo.git",
+                Some("https://github.com/org/repo"),
+            ),
+            (
+                "https://github.com/org/repo.git",
+                Some("https://github.com/org/repo"),
+            ),
+            (
+                "https://user@gitlab.com/group/sub/repo/",
+                Some("https://gitlab.com/group/sub/repo"),
+            ),
+            (
+                "ssh://git@git.example.com:2222/org/repo.git",
+                Some("https://git.example.com/org/repo"),
+            ),
+            (
+                "http://gitea.local/org/repo",
+                Some("http://gitea.local/org/repo"),
+            ),
+            (
+                "https://github.com/org/repo/commit/",
+                Some("https://github.com/org/repo"),
+            ),
+            (
+                "https://gitlab.com/org/repo/-/commit",
+                Some("https://gitlab.com/org/repo"),
+            ),
+            ("not a url", None),
+        ];
+        for (url, expected) in cases {
+            assert_eq!(normalize_url(url).as_deref(), expected, "{}", url);
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use std::error::Error;
+use std::fs;
+
+use crate::config::ChangelogConfig;
+use crate::forge::ForgeLinks;
+use crate::issues::{replace_references, IssueReference};
+use crate::release_notes::{Release, ReleaseCommit};
+use crate::render::{self, CommitGroup};
+use crate::tag_format::TagFormat;
+
+const DEFAULT_TITLE: &str = "Changelog";
+
+const DEFAULT_CSS: &str = "
+body { font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Helvetica, Arial, sans-serif;
+  line-height: 1.5; color: #1f2328; max-width: 60rem; margin: 0 auto; padding: 2rem 1rem; }
+a { color: #0969da; text-decoration: none; }
+a:hover { text-decoration: underline; }
+nav.versions a { display: inline-block; margin: 0 .5rem .25rem 0; }
+form.filters label { margin-right: 1rem; white-space: nowrap; }
+section.release { border-top: 1px solid #d0d7de; margin-top: 2rem; }
+section.release h2 time, section.release h2 .compare, .prereleases h4 time { font-size: .8em;
+  font-weight: normal; color: #656d76; margin-left: .5rem; }
+li { margin: .25rem 0; }
+.commit { font-family: ui-monospace, SFMono-Regular, Menlo, monospace; font-size: .85em; }
+.author { color: #656d76; }
+.breaking, .new { font-size: .75em; font-weight: bold; text-transform: uppercase;
+  border-radius: .25rem; padding: 0 .3rem; margin-right: .25rem; }
+.breaking { color: #fff; background: #cf222e; }
+.new { color: #fff; background: #1a7f37; margin-left: .25rem; }
+.prereleases summary { cursor: pointer; color: #656d76; margin: 1rem 0; }
+.body { color: #424a53; white-space: pre-line; margin: .25rem 0 .5rem; }
+";
+
+// Toggles the groups of every release with the checkbox of their heading
+const FILTER_SCRIPT: &str = "
+document.querySelectorAll('form.filters input').forEach(function (input) {
+  input.addEventListener('change', function () {
+    document.querySelectorAll('.group').forEach(function (group) {
+      if (group.dataset.group === input.value) {
+        group.hidden = !input.checked;
+      }
+    });
+  });
+});
+";
+
+/// A standalone HTML page with every release, a link to each version and checkboxes to show
+/// or hide groups of changes.
+pub fn render_html(
+    releases: &[Release],
+    changelog_config: &ChangelogConfig,
+    links: Option<&ForgeLinks>,
+) -> Result<String, Box<dyn Error>> {
+    let title = changelog_config
+        .html
+        .title
+        .clone()
+        .unwrap_or(String::from(DEFAULT_TITLE));
+    let css = match &changelog_config.html.css_file {
+        Some(css_file) => fs::read_to_string(css_file)
+            .map_err(|e| format!("Unable to read stylesheet {}: {}", css_file, e))?,
+        None => String::from(DEFAULT_CSS),
+    };
+    let release_links = match links {
+        Some(links) => {
+            render::release_links(releases, links, &TagFormat::from_config(changelog_config))
+        }
+        None => vec![],
+    };
+
+    let mut group_titles: Vec<String> = vec![];
+    let mut rendered_releases = String::new();
+    for (i, release) in releases.iter().enumerate() {
+        let groups = render::group_commits(release, changelog_config);
+        for group in groups.iter() {
+            if !group_titles.contains(&group.title) {
+                group_titles.push(group.title.clone());
+            }
+        }
+        rendered_releases.push_str(&render_release(
+            release,
+            &groups,
+            release_links.get(i),
+            changelog_config,
+            links,
+        ));
+    }
+
+    let mut html = String::from("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n");
+    html.push_str("<meta charset=\"utf-8\">\n");
+    html.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+    html.push_str(&format!("<title>{}</title>\n", escape(&title)));
+    html.push_str(&format!("<style>{}</style>\n", css));
+    html.push_str("</head>\n<body>\n<header>\n");
+    html.push_str(&format!("<h1>{}</h1>\n", escape(&title)));
+
+    html.push_str("<nav class=\"versions\">\n");
+    for release in releases {
+        html.push_str(&format!(
+            "<a href=\"#{}\">{}</a>\n",
+            anchor(&release.version),
+            escape(&release.version)
+        ));
+    }
+    html.push_str("</nav>\n");
+
+    html.push_str("<form class=\"filters\">\n");
+    for group_title in group_titles.iter() {
+        html.push_str(&format!(
+            "<label><input type=\"checkbox\" value=\"{0}\" checked> {0}</label>\n",
+            escape(group_title)
+        ));
+    }
+    html.push_str("</form>\n</header>\n<main>\n");
+
+    html.push_str(&rendered_releases);
+    html.push_str("</main>\n");
+    html.push_str(&format!("<script>{}</script>\n", FILTER_SCRIPT));
+    html.push_str("</body>\n</html>\n");
+    Ok(html)
+}
+
+fn render_release(
+    release: &Release,
+    groups: &[CommitGroup],
+    release_link: Option<&String>,
+    changelog_config: &ChangelogConfig,
+    links: Option<&ForgeLinks>,
+) -> String {
+    let id = anchor(&release.version);
+    let mut html = format!("<section class=\"release\" id=\"{}\">\n", id);
+    html.push_str(&format!(
+        "<h2><a href=\"#{}\">{}</a> <time datetime=\"{2}\">{2}</time>",
+        id,
+        escape(&release.version),
+        escape(&release.date)
+    ));
+    if let Some(release_link) = release_link {
+        html.push_str(&format!(
+            " <a class=\"compare\" href=\"{}\">Compare</a>",
+            escape(release_link)
+        ));
+    }
+    html.push_str("</h2>\n");
+
+    for group in groups {
+        html.push_str(&format!(
+            "<div class=\"group\" data-group=\"{0}\">\n<h3>{0}</h3>\n<ul>\n",
+            escape(&group.title)
+        ));
+        for commit in group.commits.iter() {
+            html.push_str(&render_commit(commit, changelog_config, links));
+        }
+        html.push_str("</ul>\n</div>\n");
+    }
+
+    if changelog_config.contributors.show && !release.contributors.is_empty() {
+        html.push_str("<div class=\"contributors\">\n<h3>Contributors</h3>\n<ul>\n");
+        for contributor in release.contributors.iter() {
+            html.push_str(&format!("<li>{}", escape(&contributor.name)));
+            if contributor.new {
+                html.push_str("<span class=\"new\">New</span>");
+            }
+            html.push_str("</li>\n");
+        }
+        html.push_str("</ul>\n</div>\n");
+    }
+
+    if !release.prereleases.is_empty() {
+        html.push_str("<details class=\"prereleases\">\n<summary>Pre-releases</summary>\n");
+        for prerelease in release.prereleases.iter() {
+            html.push_str(&format!(
+                "<h4>{} <time datetime=\"{1}\">{1}</time></h4>\n",
+                escape(&prerelease.version),
+                escape(&prerelease.date)
+            ));
+            for group in render::group_commits(prerelease, changelog_config).iter() {
+                html.push_str(&format!(
+                    "<div class=\"group\" data-group=\"{0}\">\n<h5>{0}</h5>\n<ul>\n",
+                    escape(&group.title)
+                ));
+                for commit in group.commits.iter() {
+                    html.push_str(&render_commit(commit, changelog_config, links));
+                }
+                html.push_str("</ul>\n</div>\n");
+            }
+        }
+        html.push_str("</details>\n");
+    }
+
+    html.push_str("</section>\n");
+    html
+}
+
+fn render_commit(
+    commit: &ReleaseCommit,
+    changelog_config: &ChangelogConfig,
+    links: Option<&ForgeLinks>,
+) -> String {
+    let mut references = commit.issues.clone();
+    references.extend(commit.pull_request.clone());
+
+    let mut html = String::from("<li>");
+    if commit.breaking {
+        html.push_str("<span class=\"breaking\">Breaking</span>");
+    }
+    if let Some(scope) = &commit.scope {
+        html.push_str(&format!("<strong>{}:</strong> ", escape(scope)));
+    }
+    html.push_str(&link_html(&commit.subject, &references));
+
+    let short_hash = escape(&commit.short_hash);
+    match links {
+        Some(links) => html.push_str(&format!(
+            " <a class=\"commit\" href=\"{}\">{}</a>",
+            escape(&links.commit(&commit.hash)),
+            short_hash
+        )),
+        None => html.push_str(&format!(" <span class=\"commit\">{}</span>", short_hash)),
+    }
+    let authors: Vec<&str> = std::iter::once(commit.author.as_str())
+        .chain(
+            commit
+                .co_authors
+                .iter()
+                .map(|co_author| co_author.name.as_str()),
+        )
+        .collect();
+    html.push_str(&format!(
+        " <span class=\"author\">by {}</span>",
+        escape(&authors.join(", "))
+    ));
+
+    if let Some(breaking_change) = &commit.breaking_change {
+        html.push_str(&format!(
+            "<p class=\"body\">{}</p>",
+            link_html(breaking_change, &references)
+        ));
+    }
+    if let (true, Some(body)) = (changelog_config.include_body, &commit.body) {
+        html.push_str(&format!(
+            "<p class=\"body\">{}</p>",
+            link_html(body, &references)
+        ));
+    }
+    html.push_str("</li>\n");
+    html
+}
+
+fn link_html(text: &str, references: &[IssueReference]) -> String {
+    replace_references(&escape(text), references, |reference| {
+        format!(
+            "<a href=\"{}\">{}</a>",
+            escape(&reference.url),
+            escape(&reference.reference)
+        )
+    })
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The id of a release section, e.g. `v1.2.0` or `unreleased`.
+fn anchor(version: &str) -> String {
+    let id: String = version
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    match id.chars().next() {
+        Some(first) if first.is_ascii_digit() => format!("v{}", id),
+        _ => id,
+    }
+}
@@ -0,0 +1,172 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::error::Error;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::config::IssueTrackerConfig;
+use crate::forge::{Forge, ForgeLinks};
+use crate::template;
+
+/// An issue or pull request mentioned in a commit message.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct IssueReference {
+    /// The reference as it is written in the message, e.g. `#12` or `GINGER-812`
+    pub reference: String,
+    pub id: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct IssueTracker {
+    re_reference: Regex,
+    url: String,
+}
+
+impl IssueTracker {
+    /// Compiles the configured trackers. Without any, `#123` (and `GH-123` on GitHub) link to
+    /// the issues of the forge.
+    pub fn load(
+        configs: &[IssueTrackerConfig],
+        links: Option<&ForgeLinks>,
+    ) -> Result<Vec<IssueTracker>, Box<dyn Error>> {
+        let mut trackers = vec![];
+        for config in configs {
+            match Regex::new(&config.pattern) {
+                Ok(re_reference) => trackers.push(IssueTracker {
+                    re_reference,
+                    url: config.url.clone(),
+                }),
+                Err(e) => {
+                    return Err(format!("Invalid issue pattern {}: {}", config.pattern, e).into())
+                }
+            }
+        }
+
+        if let (true, Some(links)) = (configs.is_empty(), links) {
+            let mut patterns = vec![r"\B#(?P<id>\d+)\b"];
+            if links.forge == Forge::GitHub {
+                patterns.push(r"\bGH-(?P<id>\d+)\b");
+            }
+            for pattern in patterns {
+                trackers.push(IssueTracker {
+                    re_reference: Regex::new(pattern).unwrap(),
+                    url: links.issue("{id}"),
+                });
+            }
+        }
+        Ok(trackers)
+    }
+
+    fn references(&self, text: &str) -> Vec<IssueReference> {
+        self.re_reference
+            .captures_iter(text)
+            .map(|caps| {
+                let reference = caps[0].to_string();
+                let id = caps
+                    .name("id")
+                    .map(|id| id.as_str().to_string())
+                    .unwrap_or(reference.clone());
+
+                // Named groups of the pattern can be used in the URL template as well
+                let mut variables: HashMap<&str, String> = self
+                    .re_reference
+                    .capture_names()
+                    .flatten()
+                    .filter_map(|name| caps.name(name).map(|value| (name, value.as_str().into())))
+                    .collect();
+                variables.insert("reference", reference.clone());
+                variables.insert("id", id.clone());
+
+                IssueReference {
+                    url: template::render(&self.url, &variables)
+                        .trim_end()
+                        .to_string(),
+                    reference,
+                    id,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Every reference the trackers find in `texts`, in order of appearance and without duplicates.
+pub fn find_references(trackers: &[IssueTracker], texts: &[&str]) -> Vec<IssueReference> {
+    let mut references: Vec<IssueReference> = vec![];
+    for text in texts {
+        for tracker in trackers {
+            for reference in tracker.references(text) {
+                if !references
+                    .iter()
+                    .any(|found| found.reference == reference.reference)
+                {
+                    references.push(reference);
+                }
+            }
+        }
+    }
+    references
+}
+
+/// Turns the references mentioned in `text` into Markdown links.
+pub fn link_references(text: &str, references: &[IssueReference]) -> String {
+    replace_references(text, references, markdown_link)
+}
+
+pub fn markdown_link(reference: &IssueReference) -> String {
+    format!("[{}]({})", reference.reference, reference.url)
+}
+
+/// Replaces the references mentioned in `text` with what `link` makes of them.
+pub fn replace_references(
+    text: &str,
+    references: &[IssueReference],
+    link: impl Fn(&IssueReference) -> String,
+) -> String {
+    split_references(text, references)
+        .into_iter()
+        .map(|(part, reference)| match reference {
+            Some(reference) => link(reference),
+            None => part.to_string(),
+        })
+        .collect()
+}
+
+/// Splits `text` into the references mentioned in it and the text around them, in order.
+pub fn split_references<'a>(
+    text: &'a str,
+    references: &'a [IssueReference],
+) -> Vec<(&'a str, Option<&'a IssueReference>)> {
+    // Longer references first so that `#123` is not taken for `#12`
+    let mut sorted: Vec<&IssueReference> = references
+        .iter()
+        .filter(|reference| !reference.reference.is_empty())
+        .collect();
+    sorted.sort_by_key(|reference| Reverse(reference.reference.len()));
+
+    let mut parts = vec![];
+    let mut text_start = 0;
+    let mut i = 0;
+    while let Some(c) = text[i..].chars().next() {
+        match sorted
+            .iter()
+            .find(|reference| text[i..].starts_with(&reference.reference))
+        {
+            Some(reference) => {
+                if text_start < i {
+                    parts.push((&text[text_start..i], None));
+                }
+                let end = i + reference.reference.len();
+                parts.push((&text[i..end], Some(*reference)));
+                i = end;
+                text_start = end;
+            }
+            None => i += c.len_utf8(),
+        }
+    }
+    if text_start < text.len() {
+        parts.push((&text[text_start..], None));
+    }
+    parts
+}
@@ -0,0 +1,297 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use git2::Repository;
+use regex::Regex;
+
+use crate::commit::ConventionalCommit;
+use crate::config::ChangelogConfig;
+use crate::filter::VERSION_BUMP_PATTERN;
+
+// Written at the top of the hooks we install, so they can be told apart from hand-written ones
+const HOOK_MARKER: &str = "# Installed by ginger-releaser install-hooks";
+
+// Messages git writes itself, or that are squashed away before they reach the history
+const EXEMPT_PREFIXES: [&str; 5] = ["Merge ", "Revert \"", "fixup! ", "squash! ", "amend! "];
+
+// The release commit is written by `release` itself, whichever types are allowed
+static RE_VERSION_BUMP: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(VERSION_BUMP_PATTERN).unwrap());
+
+/// The problems with a commit message, empty when it follows the conventions the changelog
+/// relies on.
+pub fn lint_message(message: &str, changelog_config: &ChangelogConfig) -> Vec<String> {
+    let message = message.trim();
+    if EXEMPT_PREFIXES
+        .iter()
+        .any(|prefix| message.starts_with(prefix))
+        || RE_VERSION_BUMP.is_match(message)
+    {
+        return vec![];
+    }
+
+    let mut problems: Vec<String> = vec![];
+    let mut lines = message.lines();
+    let header = lines.next().unwrap_or_default();
+    if header.trim().is_empty() {
+        return vec![String::from("the message is empty")];
+    }
+    if header.chars().count() > changelog_config.lint.max_header_length {
+        problems.push(format!(
+            "the first line is {} characters long, the limit is {}",
+            header.chars().count(),
+            changelog_config.lint.max_header_length
+        ));
+    }
+    if lines.next().is_some_and(|line| !line.trim().is_empty()) {
+        problems.push(String::from(
+            "the first line has to be followed by a blank line",
+        ));
+    }
+
+    let parsed = ConventionalCommit::parse(message);
+    let commit_type = match &parsed.commit_type {
+        Some(commit_type) => commit_type,
+        None => {
+            problems.push(String::from(
+                "the first line has to read `type(scope): description`",
+            ));
+            return problems;
+        }
+    };
+
+    let allowed_types = changelog_config.allowed_types();
+    if !allowed_types.contains(commit_type) {
+        problems.push(format!(
+            "`{}` is not an allowed type, use one of {}",
+            commit_type,
+            allowed_types.join(", ")
+        ));
+    }
+    if let Some(scope) = &parsed.scope {
+        let scopes = &changelog_config.lint.scopes;
+        if !scopes.is_empty() && !scopes.contains(scope) {
+            problems.push(format!(
+                "`{}` is not an allowed scope, use one of {}",
+                scope,
+                scopes.join(", ")
+            ));
+        }
+    }
+    problems
+}
+
+/// Lints the message file git hands to the `commit-msg` hook.
+pub fn lint_commit_file(
+    path: &str,
+    changelog_config: &ChangelogConfig,
+) -> Result<(), Box<dyn Error>> {
+    let contents = fs::read_to_string(path)?;
+    // Git leaves its comments, and with `--verbose` the diff below the scissors line, in the file
+    let message: Vec<&str> = contents
+        .lines()
+        .take_while(|line| !line.starts_with("# ------------------------ >8"))
+        .filter(|line| !line.starts_with('#'))
+        .collect();
+
+    let problems = lint_message(&message.join("\n"), changelog_config);
+    if problems.is_empty() {
+        return Ok(());
+    }
+    for problem in problems.iter() {
+        println!("{}", problem);
+    }
+    Err(format!(
+        "The commit message does not follow the conventions, {} problem(s) found",
+        problems.len()
+    )
+    .into())
+}
+
+/// Lints the message of every commit in `range`, e.g. `origin/main..HEAD`.
+pub fn lint_commit_range(
+    range: &str,
+    changelog_config: &ChangelogConfig,
+) -> Result<(), Box<dyn Error>> {
+    let repo = Repository::discover(".")?;
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push_range(range)?;
+
+    let mut failed = 0;
+    for commit_id in revwalk {
+        let commit = repo.find_commit(commit_id?)?;
+        let message = commit.message().unwrap_or_default();
+        let problems = lint_message(message, changelog_config);
+        if problems.is_empty() {
+            continue;
+        }
+        failed += 1;
+        println!(
+            "{} {}",
+            &commit.id().to_string()[..10],
+            message.lines().next().unwrap_or_default()
+        );
+        for problem in problems.iter() {
+            println!("  - {}", problem);
+        }
+    }
+
+    if failed == 0 {
+        return Ok(());
+    }
+    Err(format!("{} commit(s) do not follow the conventions", failed).into())
+}
+
+/// Writes a `commit-msg` hook running `lint-commit` from the current directory, where
+/// releaser.toml is. Hooks not written by this command are only replaced with `force`.
+pub fn install_hooks(force: bool) -> Result<(), Box<dyn Error>> {
+    let repo = Repository::discover(".")?;
+    let workdir = repo
+        .workdir()
+        .ok_or("Hooks can not be installed in a bare repository")?;
+    // Worktrees share the hooks of the main repository, which their `commondir` file points at
+    let git_dir = match fs::read_to_string(repo.path().join("commondir")) {
+        Ok(common_dir) => repo.path().join(common_dir.trim()),
+        Err(_) => repo.path().to_path_buf(),
+    };
+    let hooks_dir = match repo.config()?.get_path("core.hooksPath") {
+        Ok(hooks_path) => workdir.join(hooks_path),
+        Err(_) => git_dir.join("hooks"),
+    };
+    let hook_path = hooks_dir.join("commit-msg");
+
+    if let Ok(existing) = fs::read_to_string(&hook_path) {
+        if !existing.contains(HOOK_MARKER) && !force {
+            return Err(format!(
+                "{} already exists, use --force to replace it",
+                hook_path.display()
+            )
+            .into());
+        }
+    }
+
+    fs::create_dir_all(&hooks_dir)?;
+    fs::write(&hook_path, commit_msg_hook(workdir)?)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&hook_path, fs::Permissions::from_mode(0o755))?;
+    }
+    println!("Installed {}", hook_path.display());
+    Ok(())
+}
+
+// Hooks run from the root of the work tree, the project may live in a subdirectory
+fn commit_msg_hook(workdir: &Path) -> Result<String, Box<dyn Error>> {
+    let current_dir = std::env::current_dir()?.canonicalize()?;
+    let project_dir: PathBuf = current_dir
+        .strip_prefix(workdir.canonicalize()?)
+        .map(|project_dir| project_dir.to_path_buf())
+        .unwrap_or_default();
+
+    let mut hook = format!("#!/bin/sh\n{}\n", HOOK_MARKER);
+    hook.push_str("message_file=\"$1\"\n");
+    if !project_dir.as_os_str().is_empty() {
+        hook.push_str(
+            "case \"$message_file\" in /*) ;; *) message_file=\"$PWD/$message_file\" ;; esac\n",
+        );
+        hook.push_str(&format!("cd \"{}\" || exit 1\n", project_dir.display()));
+    }
+    hook.push_str("exec ginger-releaser lint-commit \"$message_file\"\n");
+    Ok(hook)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LintConfig;
+
+    fn config(types: &[&str], scopes: &[&str]) -> ChangelogConfig {
+        ChangelogConfig {
+            lint: LintConfig {
+                types: types.iter().map(|value| value.to_string()).collect(),
+                scopes: scopes.iter().map(|value| value.to_string()).collect(),
+                max_header_length: 30,
+            },
+            ..ChangelogConfig::default()
+        }
+    }
+
+    #[test]
+    fn accepts_conventional_messages() {
+        let config = config(&["feat", "fix"], &["api"]);
+        for message in [
+            "feat: add thing",
+            "fix(api)!: drop v1\n\nUse v2 instead.",
+            "feat: add thing\n",
+        ] {
+            assert_eq!(
+                lint_message(message, &config),
+                Vec::<String>::new(),
+                "{}",
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn exempts_generated_messages() {
+        let config = config(&["feat"], &[]);
+        for message in [
+            "Merge branch 'main' into feature",
+            "Revert \"feat: add thing\"\n\nThis reverts commit 1a2b3c4d5e6f.",
+            "fixup! feat: add thing",
+            "squash! feat: add thing",
+            "amend! feat: add thing",
+            "chore: version bump to 1.2.0\n\n - [1a2b3c4](url) feat: add thing",
+        ] {
+            assert_eq!(
+                lint_message(message, &config),
+                Vec::<String>::new(),
+                "{}",
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn reports_problems() {
+        let config = config(&["feat", "fix"], &["api"]);
+        let cases = [
+            ("", vec!["the message is empty"]),
+            (
+                "added a thing",
+                vec!["the first line has to read `type(scope): description`"],
+            ),
+            (
+                "feat: add thing\nmore about it",
+                vec!["the first line has to be followed by a blank line"],
+            ),
+            (
+                "chore: tidy up",
+                vec!["`chore` is not an allowed type, use one of feat, fix"],
+            ),
+            (
+                "feat(cli): add flag",
+                vec!["`cli` is not an allowed scope, use one of api"],
+            ),
+            (
+                "feat: add a thing that is far too long",
+                vec!["the first line is 38 characters long, the limit is 30"],
+            ),
+            (
+                "docs(cli): add a thing that is too long",
+                vec![
+                    "the first line is 39 characters long, the limit is 30",
+                    "`docs` is not an allowed type, use one of feat, fix",
+                    "`cli` is not an allowed scope, use one of api",
+                ],
+            ),
+        ];
+        for (message, problems) in cases {
+            assert_eq!(lint_message(message, &config), problems, "{}", message);
+        }
+    }
+}
@@ -1,15 +1,38 @@
 use bump::{bump_channel, bump_version, BumpType};
 use clap::{Parser, Subcommand};
-use ginger_shared_rs::{read_releaser_config_file, write_releaser_config_file};
+use compose::compose_commit;
+use config::{read_changelog_config, write_releaser_config};
+use ginger_shared_rs::read_releaser_config_file;
 use init::init;
+use lint::{install_hooks, lint_commit_file, lint_commit_range};
+use release_notes::{
+    check_release_notes, export_release_notes, generate_release_notes, print_release_notes,
+};
+use render::ChangelogFormat;
 use snapshot::generate_snapshot;
+use std::process::exit;
 use utils::update_project_source;
 
 mod bump;
+mod cache;
+mod changelog_file;
+mod commit;
+mod compose;
+mod config;
+mod contributors;
+mod filter;
+mod forge;
+mod html;
 mod init;
+mod issues;
+mod lint;
+mod ranges;
 mod references;
 mod release_notes;
+mod render;
 mod snapshot;
+mod tag_format;
+mod template;
 mod utils;
 
 #[derive(Subcommand, Debug)]
@@ -20,9 +43,58 @@ enum Commands {
     Release {
         #[command(subcommand)]
         bump_type: BumpType,
+        /// Regenerate the whole CHANGELOG.md from git instead of adding the new release on top
+        #[arg(long)]
+        rebuild: bool,
+    },
+    /// Generates the release notes, as CHANGELOG.md or as JSON/YAML for other tools
+    Changelog {
+        #[arg(long, value_enum, default_value = "markdown")]
+        format: ChangelogFormat,
+        /// File to write JSON/YAML release notes to, printed to stdout when omitted
+        #[arg(long)]
+        output: Option<String>,
+        /// Regenerate the whole CHANGELOG.md from git instead of adding the pending release on top
+        #[arg(long)]
+        rebuild: bool,
+        /// Check that CHANGELOG.md matches the git tags instead of writing it
+        #[arg(long, conflicts_with_all = ["rebuild", "output"])]
+        check: bool,
+    },
+    /// Prints the release notes of one version, or of a range of commits, to stdout
+    Notes {
+        /// Version to print the notes of, defaults to the latest tagged release
+        version: Option<String>,
+        /// Start of the commit range, exclusive
+        #[arg(long)]
+        from: Option<String>,
+        /// End of the commit range, defaults to HEAD
+        #[arg(long, requires = "from")]
+        to: Option<String>,
+    },
+    /// Asks for the type, scope, subject and footers of a commit and commits the staged changes
+    Commit,
+    /// Checks that commit messages follow the conventions the changelog is built from
+    LintCommit {
+        /// File holding the commit message, as passed to the commit-msg hook
+        #[arg(required_unless_present = "range")]
+        file: Option<String>,
+        /// Lint the commits in a range instead, e.g. origin/main..HEAD
+        #[arg(long, conflicts_with = "file")]
+        range: Option<String>,
+    },
+    /// Installs a commit-msg git hook that runs lint-commit
+    InstallHooks {
+        /// Replace an existing commit-msg hook that was not installed by ginger-releaser
+        #[arg(long)]
+        force: bool,
     },
     /// Bumps channel in the order nighly < alpha < beta < final
-    Bump,
+    Bump {
+        /// Regenerate the whole CHANGELOG.md from git instead of adding the new release on top
+        #[arg(long)]
+        rebuild: bool,
+    },
 }
 
 #[derive(Parser, Debug)]
@@ -48,21 +120,102 @@ async fn main() {
             }
             Ok(_) => {}
         },
-        Commands::Bump => {
+        Commands::Changelog {
+            format,
+            output,
+            rebuild,
+            check,
+        } => {
+            let config = read_releaser_config_file(file_path).unwrap();
+            let changelog_config = read_changelog_config(file_path).unwrap();
+            let git_url_prefix = config.settings.git_url_prefix.clone().unwrap_or_default();
+            if check {
+                if let Err(e) =
+                    check_release_notes(&git_url_prefix, config.version, &changelog_config)
+                {
+                    println!("{}", e);
+                    exit(1);
+                }
+                return;
+            }
+            let result = if format == ChangelogFormat::Markdown {
+                generate_release_notes(&git_url_prefix, config.version, &changelog_config, rebuild)
+            } else {
+                export_release_notes(
+                    &git_url_prefix,
+                    config.version,
+                    &changelog_config,
+                    &format,
+                    output.as_deref(),
+                )
+            };
+            if let Err(e) = result {
+                println!("Unable to generate {:?}", e);
+                exit(1);
+            }
+        }
+        Commands::Notes { version, from, to } => {
+            let config = read_releaser_config_file(file_path).unwrap();
+            let changelog_config = read_changelog_config(file_path).unwrap();
+            if let Err(e) = print_release_notes(
+                &config.settings.git_url_prefix.clone().unwrap_or_default(),
+                config.version,
+                &changelog_config,
+                version.as_deref(),
+                from.as_deref(),
+                to.as_deref(),
+            ) {
+                println!("Unable to generate {:?}", e);
+                exit(1);
+            }
+        }
+        Commands::Commit => {
+            let changelog_config = read_changelog_config(file_path).unwrap();
+            if let Err(e) = compose_commit(&changelog_config) {
+                println!("Unable to commit {:?}", e);
+                exit(1);
+            }
+        }
+        Commands::LintCommit { file, range } => {
+            let changelog_config = read_changelog_config(file_path).unwrap();
+            let result = match (file, range) {
+                (_, Some(range)) => lint_commit_range(&range, &changelog_config),
+                (Some(file), None) => lint_commit_file(&file, &changelog_config),
+                (None, None) => Ok(()),
+            };
+            if let Err(e) = result {
+                println!("{}", e);
+                exit(1);
+            }
+        }
+        Commands::InstallHooks { force } => {
+            if let Err(e) = install_hooks(force) {
+                println!("Unable to install hooks {:?}", e);
+                exit(1);
+            }
+        }
+        Commands::Bump { rebuild } => {
             let mut config = read_releaser_config_file(file_path).unwrap();
+            let changelog_config = read_changelog_config(file_path).unwrap();
             bump_channel(&mut config.version);
-            write_releaser_config_file(file_path, &config).unwrap();
-            update_project_source(&config, false)
+            write_releaser_config(file_path, &config).unwrap();
+            update_project_source(&config, &changelog_config, false, rebuild)
         }
-        Commands::Release { bump_type } => {
+        Commands::Release { bump_type, rebuild } => {
             let mut config = read_releaser_config_file(file_path).unwrap();
+            let changelog_config = read_changelog_config(file_path).unwrap();
 
             bump_version(bump_type.clone(), &mut config.version);
-            write_releaser_config_file(file_path, &config).unwrap();
+            write_releaser_config(file_path, &config).unwrap();
             if config.settings.take_snapshots && bump_type == BumpType::Minor {
                 generate_snapshot(&config).await
             }
-            update_project_source(&config, bump_type == BumpType::Minor);
+            update_project_source(
+                &config,
+                &changelog_config,
+                bump_type == BumpType::Minor,
+                rebuild,
+            );
         }
     }
 }
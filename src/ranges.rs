@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+
+use git2::Oid;
+
+// One bit per release
+type Releases = Vec<u64>;
+
+/// Works out the release ranges while walking the history from the newest commits to the
+/// oldest. A release holds the commits its tag contains, minus those of the releases tagged on
+/// its ancestors, so a maintenance release and a release from the main branch both list what
+/// they shipped regardless of how their versions sort.
+pub struct ReleaseRanges {
+    tips: Vec<Oid>,
+    words: usize,
+    /// Releases containing the commits that are yet to be visited
+    containing: HashMap<Oid, Releases>,
+    /// Releases that come after each release, known once the commit of its tag is visited
+    successors: Vec<Releases>,
+    /// Releases a commit belongs to, per set of releases containing it
+    ranges: HashMap<Releases, Vec<usize>>,
+}
+
+impl ReleaseRanges {
+    /// `tips` are the commits of the releases, ordered by version. The last one is the pending
+    /// release, which never hides commits from the others.
+    pub fn new(tips: &[Oid]) -> ReleaseRanges {
+        let words = tips.len().div_ceil(64);
+        let mut containing: HashMap<Oid, Releases> = HashMap::new();
+        for (i, tip) in tips.iter().enumerate() {
+            let releases = containing.entry(*tip).or_insert(vec![0; words]);
+            releases[i / 64] |= 1 << (i % 64);
+        }
+
+        ReleaseRanges {
+            tips: tips.to_vec(),
+            words,
+            containing,
+            successors: vec![vec![0; words]; tips.len()],
+            ranges: HashMap::new(),
+        }
+    }
+
+    /// The releases `commit_id` belongs to. Commits have to be visited children first, as a
+    /// topological revwalk does.
+    pub fn visit(&mut self, commit_id: Oid, parent_ids: &[Oid]) -> Vec<usize> {
+        let releases = self
+            .containing
+            .remove(&commit_id)
+            .unwrap_or(vec![0; self.words]);
+        for parent_id in parent_ids {
+            let parent_releases = self
+                .containing
+                .entry(*parent_id)
+                .or_insert(vec![0; self.words]);
+            for (word, bits) in parent_releases.iter_mut().zip(releases.iter()) {
+                *word |= bits;
+            }
+        }
+
+        // Every release containing the tag of another comes after it. Of the releases tagged
+        // on the same commit the lowest version comes first.
+        let pending = self.tips.len() - 1;
+        for i in 0..self.tips.len() {
+            if self.tips[i] != commit_id || i == pending {
+                continue;
+            }
+            let mut successors = releases.clone();
+            for j in 0..=i {
+                if self.tips[j] == commit_id {
+                    successors[j / 64] &= !(1 << (j % 64));
+                }
+            }
+            self.successors[i] = successors;
+        }
+
+        if let Some(ranges) = self.ranges.get(&releases) {
+            return ranges.clone();
+        }
+        let mut own = releases.clone();
+        for i in ones(&releases) {
+            for (word, bits) in own.iter_mut().zip(self.successors[i].iter()) {
+                *word &= !bits;
+            }
+        }
+        let ranges = ones(&own);
+        self.ranges.insert(releases, ranges.clone());
+        ranges
+    }
+}
+
+fn ones(releases: &Releases) -> Vec<usize> {
+    let mut indices = vec![];
+    for (w, word) in releases.iter().enumerate() {
+        for bit in 0..64 {
+            if word & (1 << bit) != 0 {
+                indices.push(w * 64 + bit);
+            }
+        }
+    }
+    indices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn oid(n: u8) -> Oid {
+        Oid::from_bytes(&[n; 20]).unwrap()
+    }
+
+    // Visits the commits in the given order, which has to be children first
+    fn visit_all(tips: &[Oid], commits: &[(Oid, Vec<Oid>)]) -> Vec<Vec<usize>> {
+        let mut ranges = ReleaseRanges::new(tips);
+        commits
+            .iter()
+            .map(|(commit_id, parent_ids)| ranges.visit(*commit_id, parent_ids))
+            .collect()
+    }
+
+    #[test]
+    fn splits_a_linear_history() {
+        // 1 <- 2 (1.0.0) <- 3 <- 4 (1.1.0) <- 5 (HEAD)
+        let tips = [oid(2), oid(4), oid(5)];
+        let commits = [
+            (oid(5), vec![oid(4)]),
+            (oid(4), vec![oid(3)]),
+            (oid(3), vec![oid(2)]),
+            (oid(2), vec![oid(1)]),
+            (oid(1), vec![]),
+        ];
+        assert_eq!(
+            visit_all(&tips, &commits),
+            vec![vec![2], vec![1], vec![1], vec![0], vec![0]]
+        );
+    }
+
+    #[test]
+    fn keeps_maintenance_releases_apart() {
+        // 1 <- 2 (8.26.0) <- 3 <- 4 (8.27.0) <- 6 (HEAD, merges 5)
+        //            ^- 5 (8.26.1) ------------------'
+        let tips = [oid(2), oid(5), oid(4), oid(6)];
+        let commits = [
+            (oid(6), vec![oid(4), oid(5)]),
+            (oid(5), vec![oid(2)]),
+            (oid(4), vec![oid(3)]),
+            (oid(3), vec![oid(2)]),
+            (oid(2), vec![oid(1)]),
+            (oid(1), vec![]),
+        ];
+        // The backported fix is only in 8.26.1, neither in 8.27.0 nor again in HEAD
+        assert_eq!(
+            visit_all(&tips, &commits),
+            vec![vec![3], vec![1], vec![2], vec![2], vec![0], vec![0]]
+        );
+    }
+
+    #[test]
+    fn gives_commits_tagged_twice_to_the_lowest_version() {
+        // 1 <- 2 (1.1.0-beta.0 and 1.1.0, HEAD)
+        let tips = [oid(2), oid(2), oid(2)];
+        let commits = [(oid(2), vec![oid(1)]), (oid(1), vec![])];
+        assert_eq!(visit_all(&tips, &commits), vec![vec![0], vec![0]]);
+    }
+
+    #[test]
+    fn handles_more_than_64_releases() {
+        let tips: Vec<Oid> = (1..=70).map(oid).collect();
+        let commits: Vec<(Oid, Vec<Oid>)> = (1..=70)
+            .rev()
+            .map(|n| (oid(n), if n > 1 { vec![oid(n - 1)] } else { vec![] }))
+            .collect();
+        let expected: Vec<Vec<usize>> = (0..70).rev().map(|i| vec![i]).collect();
+        assert_eq!(visit_all(&tips, &commits), expected);
+    }
+}
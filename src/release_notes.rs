@@ -1,234 +1,1021 @@
+use crate::cache::{self, CommitCache};
+use crate::changelog_file::ChangelogFile;
+use crate::commit::ConventionalCommit;
+use crate::config::{ChangelogConfig, ChangelogStyle, PrereleaseMode};
+use crate::contributors::{self, CoAuthor, Contributor};
+use crate::filter::{self, CommitFilter, PathFilter};
+use crate::forge::ForgeLinks;
+use crate::html;
+use crate::issues::{self, IssueReference, IssueTracker};
+use crate::ranges::ReleaseRanges;
+use crate::render::{self, ChangelogFormat};
+use crate::tag_format::TagFormat;
+use crate::template::{self, Templates};
 use chrono::Utc;
-use ginger_shared_rs::Version;
-use git2::{Oid, Repository};
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::Write;
-use std::process::exit;
+use ginger_shared_rs::{Channel, Version};
+use git2::{Commit, Oid, Repository, Sort};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
 
-pub fn generate_release_notes(
-    git_url_prefix: &String,
+pub const UNRELEASED: &str = "Unreleased";
+
+static RE_PULL_REQUEST: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^Merge pull request #(?P<number>\d+) from (?P<branch>\S+)").unwrap()
+});
+static RE_HASH: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\b[0-9a-f]{7,40}\b").unwrap());
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ReleaseCommit {
+    pub hash: String,
+    pub short_hash: String,
+    pub author: String,
+    pub email: String,
+    #[serde(default)]
+    pub co_authors: Vec<CoAuthor>,
+    pub date: String,
+    #[serde(rename = "type")]
+    pub commit_type: Option<String>,
+    pub scope: Option<String>,
+    pub subject: String,
+    pub body: Option<String>,
+    pub message: String,
+    pub breaking: bool,
+    pub breaking_change: Option<String>,
+    /// Issues and pull requests referenced in the subject or the footers
+    #[serde(default)]
+    pub issues: Vec<IssueReference>,
+    /// Hash of the reverted commit, for reverts of commits from earlier releases
+    #[serde(default)]
+    pub reverts: Option<String>,
+    /// The pull request a merge commit merged, in first-parent mode
+    #[serde(default)]
+    pub pull_request: Option<IssueReference>,
+}
+
+impl ReleaseCommit {
+    pub fn from_commit(commit: &Commit, trackers: &[IssueTracker]) -> ReleaseCommit {
+        ReleaseCommit::from_message(commit, commit.message().unwrap_or_default(), trackers)
+    }
+
+    /// A merge commit stands for everything it merged. GitHub pull request merges read as the
+    /// pull request title, taken from the merge body, followed by the pull request number.
+    pub fn from_merge(
+        commit: &Commit,
+        trackers: &[IssueTracker],
+        links: Option<&ForgeLinks>,
+    ) -> ReleaseCommit {
+        let message = commit.message().unwrap_or_default();
+        let caps = match RE_PULL_REQUEST.captures(message.trim()) {
+            Some(caps) => caps,
+            None => return ReleaseCommit::from_commit(commit, trackers),
+        };
+
+        let number = &caps["number"];
+        let title = match message.trim().split_once('\n') {
+            Some((_, body)) if !body.trim().is_empty() => body.trim(),
+            _ => &caps["branch"],
+        };
+        let (subject, rest) = title.split_once('\n').unwrap_or((title, ""));
+        let pull_request_message = format!("{} (#{})\n{}", subject.trim(), number, rest);
+
+        let mut release_commit =
+            ReleaseCommit::from_message(commit, pull_request_message.trim(), trackers);
+        let pull_request = IssueReference {
+            reference: format!("#{}", number),
+            id: number.to_string(),
+            url: links
+                .map(|links| links.pull_request(number))
+                .unwrap_or_default(),
+        };
+        // The number added to the subject is the pull request, not an issue
+        release_commit
+            .issues
+            .retain(|issue| issue.reference != pull_request.reference);
+        release_commit.pull_request = Some(pull_request);
+        release_commit
+    }
+
+    fn from_message(commit: &Commit, message: &str, trackers: &[IssueTracker]) -> ReleaseCommit {
+        let parsed = ConventionalCommit::parse(message);
+        let mut texts = vec![parsed.description.as_str()];
+        texts.extend(parsed.trailers.iter().map(|(_, value)| value.as_str()));
+        let issues = issues::find_references(trackers, &texts);
+        let commit_hash = commit.id().to_string();
+        let author = commit.author();
+
+        ReleaseCommit {
+            short_hash: commit_hash[..10].to_string(),
+            hash: commit_hash,
+            author: author.name().unwrap_or_default().to_string(),
+            email: author.email().unwrap_or_default().to_string(),
+            co_authors: contributors::parse_co_authors(&parsed.trailers)
+                .into_iter()
+                .filter(|co_author| {
+                    !co_author
+                        .email
+                        .eq_ignore_ascii_case(author.email().unwrap_or_default())
+                })
+                .collect(),
+            date: format_date(commit.time().seconds()),
+            breaking_change: parsed.breaking_change().map(|text| text.to_string()),
+            commit_type: parsed.commit_type,
+            scope: parsed.scope,
+            subject: parsed.description,
+            body: parsed.body,
+            message: message.to_string(),
+            breaking: parsed.breaking,
+            issues,
+            reverts: parsed.reverts,
+            pull_request: None,
+        }
+    }
+}
+
+/// A tagged release, or the pending one when `tag` is `None`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Release {
+    pub version: String,
+    pub date: String,
+    pub tag: Option<String>,
+    pub commits: Vec<ReleaseCommit>,
+    #[serde(default)]
+    pub contributors: Vec<Contributor>,
+    /// The pre-releases rolled up into this release, newest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prereleases: Vec<Release>,
+}
+
+fn format_date(seconds: i64) -> String {
+    chrono::DateTime::from_timestamp(seconds, 0)
+        .unwrap()
+        .date_naive()
+        .to_string()
+}
+
+/// The settings that decide which commits are read from the history and how, along with
+/// the commits parsed so far.
+pub struct CommitReader {
+    tag_format: TagFormat,
+    tagger_date: bool,
+    prereleases: PrereleaseMode,
+    links: Option<ForgeLinks>,
+    trackers: Vec<IssueTracker>,
+    filter: CommitFilter,
+    paths: PathFilter,
+    first_parent: bool,
+    bots: Vec<Regex>,
+    cache: CommitCache,
+}
+
+impl CommitReader {
+    pub fn load(
+        repo: &Repository,
+        changelog_config: &ChangelogConfig,
+        links: Option<ForgeLinks>,
+    ) -> Result<CommitReader, Box<dyn Error>> {
+        // In first-parent mode the merge commits are the entries
+        let mut exclude = changelog_config.exclude.clone();
+        if changelog_config.first_parent {
+            exclude.merge_commits = false;
+        }
+
+        // Cached commits are only valid for the settings that went into parsing them. Issue
+        // and pull request links, and the default trackers, depend on the forge.
+        let cache_key = format!(
+            "{}|{}|{}|{}|{}|{}",
+            env!("CARGO_PKG_VERSION"),
+            serde_json::to_string(&changelog_config.issues)?,
+            serde_json::to_string(&links.as_ref().map(|links| &links.forge))?,
+            links
+                .as_ref()
+                .map(|links| links.base_url.as_str())
+                .unwrap_or_default(),
+            changelog_config.first_parent,
+            serde_json::to_string(&changelog_config.paths)?
+        );
+        let cache_dir = match &changelog_config.cache_dir {
+            Some(cache_dir) => PathBuf::from(cache_dir),
+            None => repo.path().join(cache::CACHE_DIR),
+        };
+
+        Ok(CommitReader {
+            tag_format: TagFormat::from_config(changelog_config),
+            tagger_date: changelog_config.tagger_date,
+            prereleases: changelog_config.prereleases.clone(),
+            trackers: IssueTracker::load(&changelog_config.issues, links.as_ref())?,
+            links,
+            filter: CommitFilter::load(&exclude)?,
+            paths: PathFilter::load(&changelog_config.paths)?,
+            first_parent: changelog_config.first_parent,
+            bots: filter::compile(&changelog_config.contributors.bots)?,
+            cache: CommitCache::load(&cache_dir, &cache_key),
+        })
+    }
+
+    fn read(&mut self, commit: &Commit) -> ReleaseCommit {
+        let commit_id = commit.id().to_string();
+        if let Some(cached) = self.cache.get(&commit_id) {
+            return cached.clone();
+        }
+
+        let release_commit = if self.first_parent && commit.parent_count() > 1 {
+            ReleaseCommit::from_merge(commit, &self.trackers, self.links.as_ref())
+        } else {
+            ReleaseCommit::from_commit(commit, &self.trackers)
+        };
+        self.cache.insert(release_commit.clone());
+        release_commit
+    }
+
+    /// Whether the commit changes the paths the changelog is limited to. Diffing every commit
+    /// is slow on long histories, so the answer is cached along with the parsed commits.
+    fn touches(&mut self, repo: &Repository, commit: &Commit) -> Result<bool, git2::Error> {
+        if !self.paths.is_active() {
+            return Ok(true);
+        }
+        let commit_id = commit.id().to_string();
+        if let Some(touches) = self.cache.touches(&commit_id) {
+            return Ok(touches);
+        }
+
+        let touches = self.paths.touches(repo, commit)?;
+        self.cache.insert_touches(&commit_id, touches);
+        Ok(touches)
+    }
+
+    /// Writes the newly parsed commits to the cache. The cache only saves time, so failing to
+    /// write it is not an error.
+    fn save_cache(&self) {
+        if let Err(e) = self.cache.save() {
+            eprintln!("Unable to write the commit cache: {}", e);
+        }
+    }
+}
+
+fn collect_commits(
+    repo: &Repository,
+    from: Oid,
+    hide: Option<Oid>,
+    reader: &mut CommitReader,
+) -> Result<Vec<ReleaseCommit>, git2::Error> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    if reader.first_parent {
+        revwalk.simplify_first_parent()?;
+    }
+    revwalk.push(from)?;
+    if let Some(hide) = hide {
+        revwalk.hide(hide)?;
+    }
+
+    let mut commits = vec![];
+    for commit_id in revwalk {
+        let commit = repo.find_commit(commit_id?)?;
+        if reader.filter.is_excluded(&commit) || !reader.touches(repo, &commit)? {
+            continue;
+        }
+        commits.push(reader.read(&commit));
+    }
+    reader.save_cache();
+    Ok(cancel_reverts(commits))
+}
+
+/// Drops reverts together with the commit they revert when both are in the same release, as
+/// nothing of either ships. `commits` are newest first, so a revert of a revert cancels out
+/// before the original revert is looked at.
+fn cancel_reverts(commits: Vec<ReleaseCommit>) -> Vec<ReleaseCommit> {
+    let mut cancelled: Vec<String> = vec![];
+    for commit in commits.iter() {
+        let reverted = match &commit.reverts {
+            Some(reverted) if !cancelled.contains(&commit.hash) => reverted,
+            _ => continue,
+        };
+        if let Some(original) = commits.iter().find(|original| {
+            original.hash.starts_with(reverted.as_str()) && !cancelled.contains(&original.hash)
+        }) {
+            cancelled.push(commit.hash.clone());
+            cancelled.push(original.hash.clone());
+        }
+    }
+
+    commits
+        .into_iter()
+        .filter(|commit| !cancelled.contains(&commit.hash))
+        .collect()
+}
+
+fn is_final(release: &Release) -> bool {
+    release.version != UNRELEASED && Version::from_str(&release.version).channel == Channel::Final
+}
+
+/// Adds the commits of the nightly, alpha and beta releases to the first final release that
+/// sorts after them. Pre-releases with no final release yet keep their own section.
+fn roll_up_prereleases(releases: Vec<Release>, mode: &PrereleaseMode) -> Vec<Release> {
+    if *mode == PrereleaseMode::Separate {
+        return releases;
+    }
+
+    let mut rolled_up: Vec<Release> = vec![];
+    let mut prereleases: Vec<Release> = vec![];
+    // Oldest release first
+    for mut release in releases.into_iter().rev() {
+        if release.version == UNRELEASED {
+            rolled_up.append(&mut prereleases);
+        } else if !is_final(&release) {
+            prereleases.push(release);
+            continue;
+        } else if !prereleases.is_empty() {
+            // A commit on two branches can belong to more than one pre-release
+            let mut listed: HashSet<String> = HashSet::new();
+            let commits: Vec<ReleaseCommit> = release
+                .commits
+                .iter()
+                .chain(
+                    prereleases
+                        .iter()
+                        .rev()
+                        .flat_map(|prerelease| prerelease.commits.iter()),
+                )
+                .filter(|commit| listed.insert(commit.hash.clone()))
+                .cloned()
+                .collect();
+            release.commits = cancel_reverts(commits);
+            if *mode == PrereleaseMode::Collapse {
+                prereleases.reverse();
+                release.prereleases = std::mem::take(&mut prereleases);
+            } else {
+                prereleases.clear();
+            }
+        }
+        rolled_up.push(release);
+    }
+    rolled_up.append(&mut prereleases);
+    rolled_up.reverse();
+    // Unless pre-releases rolled up into it, the pending release is empty
+    rolled_up.retain(|release| release.tag.is_some() || !release.commits.is_empty());
+    rolled_up
+}
+
+/// Walks the git history and collects every release, newest first. Each release lists the
+/// commits since the releases tagged on its ancestors. Commits that are in no release yet are
+/// collected under `version`, or under "Unreleased" when `version` is already tagged.
+pub fn collect_releases(
+    repo: &Repository,
     version: Version,
-) -> Result<(), git2::Error> {
-    let repo = Repository::open(".")?;
+    reader: &mut CommitReader,
+) -> Result<Vec<Release>, git2::Error> {
     let mut tags: HashMap<String, Oid> = HashMap::new();
-    let mut release_notes: HashMap<String, HashMap<String, Vec<String>>> = HashMap::new();
     let mut tag_dates: HashMap<String, String> = HashMap::new();
+    let mut tag_versions: HashMap<String, String> = HashMap::new();
     let mut sorted_tags: Vec<String> = vec![];
-    // Get all tags in the repository and filter by semantic versioning
-    for tag in repo.tag_names(None)?.iter() {
-        match tag {
-            Some(version) => {
-                let tag_ref = format!("refs/tags/{}", &version);
-                match repo.refname_to_id(&tag_ref) {
-                    Ok(tag_id) => {
-                        // Proceed to find the commit and its date
-                        tags.insert(version.to_string(), tag_id);
-                        match repo.find_commit(tag_id) {
-                            Ok(commit) => {
-                                let tag_date = commit.time().seconds();
-                                let tag_datetime = chrono::DateTime::from_timestamp(tag_date, 0);
-                                tag_dates.insert(
-                                    version.to_string(),
-                                    tag_datetime.unwrap().date_naive().to_string(),
-                                );
-                                sorted_tags.push(version.to_string());
-                                release_notes.insert(version.to_string(), HashMap::new());
-                            }
-                            Err(e) => {
-                                eprintln!("Failed to find commit for tag {}: {}", version, e);
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("Failed to get tag ID for {}: {}", version, e);
-                    }
-                }
+    // Get all tags in the repository that follow the tag format
+    for tag in repo.tag_names(None)?.iter().flatten() {
+        let tag_version = match reader.tag_format.version(tag) {
+            Some(tag_version) => tag_version,
+            None => continue,
+        };
+        let tag_ref = format!("refs/tags/{}", &tag);
+        // Annotated tags point at a tag object rather than at the commit itself
+        let reference = match repo.find_reference(&tag_ref) {
+            Ok(reference) => reference,
+            Err(e) => {
+                eprintln!("Failed to get tag ID for {}: {}", tag, e);
+                continue;
+            }
+        };
+        match reference.peel_to_commit() {
+            Ok(commit) => {
+                let seconds = match reference.peel_to_tag() {
+                    Ok(annotated) if reader.tagger_date => annotated
+                        .tagger()
+                        .map(|tagger| tagger.when().seconds())
+                        .unwrap_or(commit.time().seconds()),
+                    _ => commit.time().seconds(),
+                };
+                tags.insert(tag.to_string(), commit.id());
+                tag_dates.insert(tag.to_string(), format_date(seconds));
+                tag_versions.insert(tag.to_string(), tag_version);
+                sorted_tags.push(tag.to_string());
+            }
+            Err(e) => {
+                eprintln!("Failed to find commit for tag {}: {}", tag, e);
             }
-            None => {}
         }
     }
 
+    // Oldest release first, the pending release goes last
     sorted_tags.sort_by(|a, b| {
-        let semver_a = Version::from_str(a);
-        let semver_b = Version::from_str(b);
-        semver_b.cmp(&semver_a)
+        let semver_a = Version::from_str(&tag_versions[a]);
+        let semver_b = Version::from_str(&tag_versions[b]);
+        semver_a.cmp(&semver_b)
     });
+    let pending = sorted_tags.len();
 
-    // Iterate over tags and collect commit messages
-    for (i, tag_name) in sorted_tags.iter().enumerate() {
-        let mut revwalk = repo.revwalk()?;
-        // let mut commit_messages: HashMap<String, Vec<String>> = HashMap::new();
+    let head_oid = repo
+        .head()?
+        .target()
+        .ok_or_else(|| git2::Error::from_str("No commit in HEAD"))?;
 
-        if i < sorted_tags.len() - 1 {
-            let next_tag_name = &sorted_tags[i + 1];
+    // A single walk over the whole history, sorting every commit into the releases it
+    // belongs to as it goes
+    let mut tips: Vec<Oid> = sorted_tags.iter().map(|tag_name| tags[tag_name]).collect();
+    tips.push(head_oid);
+    let mut ranges = ReleaseRanges::new(&tips);
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    if reader.first_parent {
+        revwalk.simplify_first_parent()?;
+    }
+    for tip in tips.iter() {
+        revwalk.push(*tip)?;
+    }
 
-            let start_commit = repo.find_commit(*tags.get(next_tag_name).unwrap())?;
+    let mut release_commits: Vec<Vec<ReleaseCommit>> = vec![vec![]; pending + 1];
+    for commit_id in revwalk {
+        let commit = repo.find_commit(commit_id?)?;
+        let parent_ids: Vec<Oid> = if reader.first_parent {
+            commit.parent_ids().take(1).collect()
+        } else {
+            commit.parent_ids().collect()
+        };
+        let releases = ranges.visit(commit.id(), &parent_ids);
 
-            let end_commit = repo.find_commit(*tags.get(tag_name).unwrap())?;
+        if releases.is_empty()
+            || reader.filter.is_excluded(&commit)
+            || !reader.touches(repo, &commit)?
+        {
+            continue;
+        }
+        let release_commit = reader.read(&commit);
+        for release in releases {
+            release_commits[release].push(release_commit.clone());
+        }
+    }
+    reader.save_cache();
 
-            revwalk.push(end_commit.id())?;
-            revwalk.hide(start_commit.id())?;
+    let mut releases: Vec<Release> = vec![];
+    let pending_commits = cancel_reverts(release_commits.pop().unwrap_or_default());
+    let is_tagged = tags.contains_key(&reader.tag_format.tag(&version.formatted()));
+    // A final version shipped right after its last beta has no commits of its own, but the
+    // pre-releases before it roll up into it
+    let rolls_up = !is_tagged
+        && version.channel == Channel::Final
+        && reader.prereleases != PrereleaseMode::Separate;
+    if !pending_commits.is_empty() || rolls_up {
+        let pending_version = if is_tagged {
+            String::from(UNRELEASED)
         } else {
-            let end_commit = repo.find_commit(*tags.get(tag_name).unwrap())?;
-            revwalk.push(end_commit.id())?;
-        }
-
-        for commit_id in revwalk {
-            let commit_id = commit_id?;
-            let commit = repo.find_commit(commit_id)?;
-            let commit_hash = commit.id().to_string();
-            let author = commit.author();
-            let message = commit.message().unwrap_or_default();
-
-            let prefix = message
-                .split_whitespace()
-                .next()
-                .unwrap_or_default()
-                .to_string();
-
-            let formatted_message = format!(
-                " - [{}]({}{}) ({}) {}\n",
-                &commit_hash[..10],
-                git_url_prefix,
-                commit_hash,
-                author.name().unwrap_or_default(),
-                message.replace("\n", "\n\t")
+            version.formatted()
+        };
+        releases.push(Release {
+            version: pending_version,
+            date: Utc::now().date_naive().to_string(),
+            tag: None,
+            commits: pending_commits,
+            contributors: vec![],
+            prereleases: vec![],
+        });
+    }
+
+    for (tag_name, commits) in sorted_tags.iter().zip(release_commits).rev() {
+        releases.push(Release {
+            version: tag_versions[tag_name].clone(),
+            date: tag_dates[tag_name].clone(),
+            tag: Some(tag_name.clone()),
+            commits: cancel_reverts(commits),
+            contributors: vec![],
+            prereleases: vec![],
+        });
+    }
+    let mut releases = roll_up_prereleases(releases, &reader.prereleases);
+
+    // Contributors are new when none of the older releases has a commit by them
+    let mut known: HashSet<String> = HashSet::new();
+    for release in releases.iter_mut().rev() {
+        let mut known_before = known.clone();
+        for prerelease in release.prereleases.iter_mut().rev() {
+            prerelease.contributors = contributors::collect_contributors(
+                &prerelease.commits,
+                &reader.bots,
+                &mut known_before,
             );
+        }
+        release.contributors =
+            contributors::collect_contributors(&release.commits, &reader.bots, &mut known);
+    }
 
-            // commit_messages
-            //     .get_mut(&prefix)
-            //     .unwrap()
-            //     .push(formatted_message);
+    Ok(releases)
+}
 
-            // release_notes
-            //     .entry(tag_name)
-            //     .or_insert_with(Vec::new)
-            //     .push(formatted_message);
+pub fn generate_release_notes(
+    git_url_prefix: &str,
+    version: Version,
+    changelog_config: &ChangelogConfig,
+    rebuild: bool,
+) -> Result<(), Box<dyn Error>> {
+    let repo = Repository::discover(".")?;
+    let templates = Templates::load(&changelog_config.templates, &changelog_config.style)?;
+    let links = ForgeLinks::resolve(&repo, git_url_prefix, changelog_config.forge.as_ref());
+    let mut reader = CommitReader::load(&repo, changelog_config, links.clone())?;
+    let releases = collect_releases(&repo, version, &mut reader)?;
+    let changelog_path = Path::new("CHANGELOG.md");
 
-            release_notes
-                .entry(tag_name.clone())
-                .or_insert_with(HashMap::new)
-                .entry(prefix)
-                .or_insert_with(Vec::new)
-                .push(formatted_message);
+    // Unless a rebuild is asked for, only the pending release is added on top of the existing
+    // CHANGELOG.md so that hand edits to older releases survive
+    if !rebuild && changelog_path.exists() {
+        let mut changelog = ChangelogFile::parse(
+            &fs::read_to_string(changelog_path)?,
+            &template::heading_regex(&templates.version_heading),
+        );
+        // New sections would end up below the unrecognized ones, at the bottom of the file
+        if changelog.has_unrecognized_headings() {
+            return Err("No release heading in CHANGELOG.md matches the version heading template, use `changelog --rebuild` to regenerate it".into());
         }
-    }
 
-    // Collect commits since the last tag under "Unreleased commits"
-    let mut revwalk = repo.revwalk()?;
-    let head = repo.head()?;
+        let pending = match releases.first() {
+            Some(release) if release.tag.is_none() => release,
+            _ => {
+                println!("No changes since the last release, CHANGELOG.md left untouched");
+                return Ok(());
+            }
+        };
 
-    // Get the OID (commit ID) of the HEAD commit
-    let head_oid = head
-        .target()
-        .ok_or_else(|| git2::Error::from_str("No commit in HEAD"))?;
+        if pending.version != UNRELEASED && changelog.has_release(&pending.version) {
+            println!(
+                "CHANGELOG.md already has a section for {}, leaving it untouched",
+                pending.version
+            );
+            return Ok(());
+        }
 
-    // Find the commit object using the OID
-    let last_commit = repo.find_commit(head_oid)?;
-
-    if let Some(last_tag_name) = sorted_tags.first().cloned() {
-        let end_commit = repo.find_commit(*tags.get(&last_tag_name).unwrap())?;
-        revwalk.push(last_commit.id())?;
-        revwalk.hide(end_commit.id())?;
-        // let mut commit_messages = Vec::new();
-
-        for commit_id in revwalk {
-            let commit_id = commit_id?;
-            let commit = repo.find_commit(commit_id)?;
-            let commit_hash = commit.id().to_string();
-            let author = commit.author();
-            let message = commit.message().unwrap_or_default();
-            let prefix = message
-                .split_whitespace()
-                .next()
-                .unwrap_or_default()
-                .to_string();
-            let formatted_message = format!(
-                " - [{}]({}{}) ({}) {}\n",
-                &commit_hash[..10],
-                git_url_prefix,
-                commit_hash,
-                author.name().unwrap_or_default(),
-                message.replace("\n", "\n\t")
+        // The "Unreleased" section is generated, so it is replaced rather than kept
+        changelog.remove_release(UNRELEASED);
+        // So are the sections of the pre-releases now rolled up into the pending release
+        if changelog_config.prereleases != PrereleaseMode::Separate {
+            let rolled_up: Vec<String> = changelog
+                .releases
+                .iter()
+                .map(|existing| existing.version.clone())
+                .filter(|existing| {
+                    existing != UNRELEASED
+                        && Version::from_str(existing).channel != Channel::Final
+                        && !releases.iter().any(|release| &release.version == existing)
+                })
+                .collect();
+            for prerelease in rolled_up.iter() {
+                changelog.remove_release(prerelease);
+            }
+        }
+        changelog.insert_release(
+            &pending.version,
+            render::render_markdown_release(pending, changelog_config, &templates, links.as_ref()),
+        );
+
+        // Keep a Changelog always has an Unreleased section on top and compare links that
+        // cover every release, both are regenerated
+        if changelog_config.style == ChangelogStyle::KeepAChangelog {
+            let releases = render::with_unreleased(&releases);
+            if pending.version != UNRELEASED {
+                changelog.insert_release(
+                    UNRELEASED,
+                    render::render_markdown_release(
+                        &releases[0],
+                        changelog_config,
+                        &templates,
+                        links.as_ref(),
+                    ),
+                );
+            }
+            changelog.footer = render::render_link_definitions(
+                &releases,
+                links.as_ref(),
+                &TagFormat::from_config(changelog_config),
             );
+        }
 
-            release_notes
-                .entry(version.formatted())
-                .or_insert_with(HashMap::new)
-                .entry(prefix)
-                .or_insert_with(Vec::new)
-                .push(formatted_message);
-
-            // commit_messages.push(formatted_message);
-        }
-
-        // release_notes.insert(String::from(version.formatted()), commit_messages);
-    }
-
-    match File::create("CHANGELOG.md") {
-        Ok(mut release_notes_file) => {
-            match release_notes.get(&version.formatted()) {
-                Some(notes) => {
-                    let current_date_time = Utc::now();
-
-                    match write!(
-                        release_notes_file,
-                        "## {} - {}\n",
-                        String::from(version.formatted()),
-                        current_date_time.date_naive()
-                    ) {
-                        Ok(()) => {}
-                        Err(_) => exit(0),
-                    };
-
-                    for (section_heading, section_notes) in notes.iter() {
-                        match write!(release_notes_file, "{}\n", section_heading) {
-                            Ok(()) => {}
-                            Err(_) => exit(0),
-                        };
-                        for note in section_notes {
-                            match write!(release_notes_file, "{}", note) {
-                                Ok(()) => {}
-                                Err(_) => exit(0),
-                            };
-                        }
-                    }
-                }
-                None => {}
+        fs::write(changelog_path, changelog.render())?;
+        return Ok(());
+    }
+
+    fs::write(
+        changelog_path,
+        render::render_markdown(&releases, changelog_config, &templates, links.as_ref()),
+    )?;
+
+    Ok(())
+}
+
+/// Compares the releases in CHANGELOG.md with the ones in the git history and reports the
+/// releases that are missing, that should not be there, or that list different commits. The
+/// pending release is left out, as it is only written on release.
+pub fn check_release_notes(
+    git_url_prefix: &str,
+    version: Version,
+    changelog_config: &ChangelogConfig,
+) -> Result<(), Box<dyn Error>> {
+    let repo = Repository::discover(".")?;
+    let templates = Templates::load(&changelog_config.templates, &changelog_config.style)?;
+    let links = ForgeLinks::resolve(&repo, git_url_prefix, changelog_config.forge.as_ref());
+    let mut reader = CommitReader::load(&repo, changelog_config, links.clone())?;
+    let releases: Vec<Release> = collect_releases(&repo, version, &mut reader)?
+        .into_iter()
+        .filter(|release| release.tag.is_some())
+        .collect();
+    let changelog = ChangelogFile::parse(
+        &fs::read_to_string("CHANGELOG.md")
+            .map_err(|e| format!("Unable to read CHANGELOG.md: {}", e))?,
+        &template::heading_regex(&templates.version_heading),
+    );
+
+    let mut problems: Vec<String> = vec![];
+    for release in releases.iter() {
+        let existing = match changelog
+            .releases
+            .iter()
+            .find(|existing| existing.version == release.version)
+        {
+            Some(existing) => existing,
+            None => {
+                problems.push(format!("{} is missing from CHANGELOG.md", release.version));
+                continue;
             }
+        };
+
+        // Commits are compared by the hashes the entry template writes, if it writes any
+        if !templates.entry.contains("hash}") {
+            continue;
+        }
+        let expected = commit_hashes(&render::render_markdown_release(
+            release,
+            changelog_config,
+            &templates,
+            links.as_ref(),
+        ));
+        let found = commit_hashes(&existing.text);
+        let missing: Vec<&String> = expected.difference(&found).collect();
+        let extra: Vec<&String> = found.difference(&expected).collect();
+        if !missing.is_empty() || !extra.is_empty() {
+            problems.push(format!(
+                "{} does not match git: {} commit(s) missing, {} not in the release{}",
+                release.version,
+                missing.len(),
+                extra.len(),
+                list_hashes(&missing, &extra)
+            ));
+        }
+    }
+
+    for existing in changelog.releases.iter() {
+        let is_released = releases
+            .iter()
+            .any(|release| release.version == existing.version);
+        if existing.version == UNRELEASED || is_released {
+            continue;
+        }
+        if changelog_config.prereleases != PrereleaseMode::Separate
+            && Version::from_str(&existing.version).channel != Channel::Final
+        {
+            problems.push(format!(
+                "{} is rolled up into a final release but still has its own section",
+                existing.version
+            ));
+        } else {
+            problems.push(format!(
+                "{} is in CHANGELOG.md but has no release tag",
+                existing.version
+            ));
+        }
+    }
+
+    if problems.is_empty() {
+        println!("CHANGELOG.md is up to date");
+        return Ok(());
+    }
+    for problem in problems.iter() {
+        println!("{}", problem);
+    }
+    Err(format!(
+        "CHANGELOG.md is out of date, {} problem(s) found",
+        problems.len()
+    )
+    .into())
+}
+
+// Hashes are cut down to the shortest length git prints, as the entries may use any length
+fn commit_hashes(text: &str) -> HashSet<String> {
+    RE_HASH
+        .find_iter(text)
+        .map(|hash| hash.as_str()[..7].to_string())
+        .collect()
+}
+
+fn list_hashes(missing: &[&String], extra: &[&String]) -> String {
+    let mut listed = String::new();
+    for hash in missing {
+        listed.push_str(&format!("\n  - {} missing", hash));
+    }
+    for hash in extra {
+        listed.push_str(&format!("\n  + {} not in the release", hash));
+    }
+    listed
+}
 
-            for tag_name in sorted_tags.iter() {
-                match release_notes.get(tag_name) {
-                    Some(notes) => {
-                        match write!(
-                            release_notes_file,
-                            "## {} - {}\n",
-                            tag_name,
-                            tag_dates.get(tag_name).unwrap()
-                        ) {
-                            Ok(()) => {}
-                            Err(_) => exit(0),
-                        };
-                        for (section_heading, section_notes) in notes.iter() {
-                            match write!(release_notes_file, "{}\n", section_heading) {
-                                Ok(()) => {}
-                                Err(_) => exit(0),
-                            };
-                            for note in section_notes {
-                                match write!(release_notes_file, "{}", note) {
-                                    Ok(()) => {}
-                                    Err(_) => exit(0),
-                                };
-                            }
-                        }
+/// Prints the notes of a single release without touching CHANGELOG.md. The release is picked
+/// by `requested` version, or built from the `from..to` range, and defaults to the latest tagged
+/// one.
+pub fn print_release_notes(
+    git_url_prefix: &str,
+    version: Version,
+    changelog_config: &ChangelogConfig,
+    requested: Option<&str>,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let repo = Repository::discover(".")?;
+    let templates = Templates::load(&changelog_config.templates, &changelog_config.style)?;
+    let links = ForgeLinks::resolve(&repo, git_url_prefix, changelog_config.forge.as_ref());
+    let mut reader = CommitReader::load(&repo, changelog_config, links.clone())?;
+
+    let release = match from {
+        Some(from) => {
+            let to = to.unwrap_or("HEAD");
+            let from_oid = repo.revparse_single(from)?.peel_to_commit()?.id();
+            let to_oid = repo.revparse_single(to)?.peel_to_commit()?.id();
+            let commits = collect_commits(&repo, to_oid, Some(from_oid), &mut reader)?;
+            let mut known =
+                contributors::identities(&collect_commits(&repo, from_oid, None, &mut reader)?);
+            Release {
+                version: requested
+                    .map(|requested| requested.to_string())
+                    .unwrap_or(format!("{}...{}", from, to)),
+                date: format_date(repo.find_commit(to_oid)?.time().seconds()),
+                tag: None,
+                contributors: contributors::collect_contributors(
+                    &commits,
+                    &reader.bots,
+                    &mut known,
+                ),
+                commits,
+                prereleases: vec![],
+            }
+        }
+        None => {
+            let releases = collect_releases(&repo, version, &mut reader)?;
+            let release = match requested {
+                Some(requested) => releases.into_iter().find(|release| {
+                    release.version == requested || release.tag.as_deref() == Some(requested)
+                }),
+                // The latest tagged release, or the configured version when nothing is tagged
+                // yet. Commits after the last tag are not a release of their own.
+                None => {
+                    let pending = releases
+                        .iter()
+                        .position(|release| release.tag.is_none() && release.version != UNRELEASED);
+                    match releases.iter().position(|release| release.tag.is_some()) {
+                        Some(latest) => releases.into_iter().nth(latest),
+                        None => pending.and_then(|pending| releases.into_iter().nth(pending)),
                     }
-                    None => {}
+                }
+            };
+            match release {
+                Some(release) => release,
+                None => {
+                    return Err(
+                        format!("No release {} found", requested.unwrap_or_default()).into(),
+                    )
                 }
             }
         }
-        Err(_) => exit(0),
     };
 
+    print!(
+        "{}",
+        render::render_markdown_release(&release, changelog_config, &templates, links.as_ref())
+    );
+    Ok(())
+}
+
+/// Writes the release notes as JSON, YAML or an HTML page to `output`, or to stdout.
+pub fn export_release_notes(
+    git_url_prefix: &str,
+    version: Version,
+    changelog_config: &ChangelogConfig,
+    format: &ChangelogFormat,
+    output: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let repo = Repository::discover(".")?;
+    let links = ForgeLinks::resolve(&repo, git_url_prefix, changelog_config.forge.as_ref());
+    let mut reader = CommitReader::load(&repo, changelog_config, links.clone())?;
+    let releases = collect_releases(&repo, version, &mut reader)?;
+
+    let contents = match format {
+        ChangelogFormat::Json => render::render_json(&releases)?,
+        ChangelogFormat::Yaml => render::render_yaml(&releases)?,
+        ChangelogFormat::Html => html::render_html(&releases, changelog_config, links.as_ref())?,
+        ChangelogFormat::Markdown => {
+            return Err("Markdown release notes are written to CHANGELOG.md".into())
+        }
+    };
+
+    match output {
+        Some(output) => fs::write(output, contents)?,
+        None => println!("{}", contents),
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(hash: &str, reverts: Option<&str>) -> ReleaseCommit {
+        ReleaseCommit {
+            hash: hash.to_string(),
+            short_hash: hash[..7].to_string(),
+            author: String::from("Dev"),
+            email: String::from("dev@example.com"),
+            co_authors: vec![],
+            date: String::from("2024-01-01"),
+            commit_type: Some(String::from("feat")),
+            scope: None,
+            subject: format!("change {}", hash),
+            body: None,
+            message: format!("feat: change {}", hash),
+            breaking: false,
+            breaking_change: None,
+            issues: vec![],
+            reverts: reverts.map(String::from),
+            pull_request: None,
+        }
+    }
+
+    fn hashes(commits: &[ReleaseCommit]) -> Vec<&str> {
+        commits.iter().map(|commit| commit.hash.as_str()).collect()
+    }
+
+    #[test]
+    fn cancels_reverts_in_the_same_release() {
+        // Newest first, the revert names the commit by a shortened hash
+        let commits = vec![
+            commit("ccccccc1", Some("aaaaaaa")),
+            commit("bbbbbbb1", None),
+            commit("aaaaaaa1", None),
+        ];
+        assert_eq!(hashes(&cancel_reverts(commits)), vec!["bbbbbbb1"]);
+    }
+
+    #[test]
+    fn keeps_reverts_of_earlier_releases() {
+        let commits = vec![
+            commit("ccccccc1", Some("0000000")),
+            commit("aaaaaaa1", None),
+        ];
+        assert_eq!(
+            hashes(&cancel_reverts(commits)),
+            vec!["ccccccc1", "aaaaaaa1"]
+        );
+    }
+
+    #[test]
+    fn cancels_reverts_of_reverts() {
+        // Reverting the revert brings the original commit back
+        let commits = vec![
+            commit("ccccccc1", Some("bbbbbbb1")),
+            commit("bbbbbbb1", Some("aaaaaaa1")),
+            commit("aaaaaaa1", None),
+        ];
+        assert_eq!(hashes(&cancel_reverts(commits)), vec!["aaaaaaa1"]);
+
+        // Reverting it once more takes it out again
+        let commits = vec![
+            commit("ddddddd1", Some("ccccccc1")),
+            commit("ccccccc1", Some("bbbbbbb1")),
+            commit("bbbbbbb1", Some("aaaaaaa1")),
+            commit("aaaaaaa1", None),
+        ];
+        assert_eq!(hashes(&cancel_reverts(commits)), Vec::<&str>::new());
+    }
+
+    fn release(version: &str, tagged: bool, commits: Vec<ReleaseCommit>) -> Release {
+        Release {
+            version: version.to_string(),
+            date: String::from("2024-01-01"),
+            tag: tagged.then(|| version.to_string()),
+            commits,
+            contributors: vec![],
+            prereleases: vec![],
+        }
+    }
+
+    fn versions(releases: &[Release]) -> Vec<&str> {
+        releases
+            .iter()
+            .map(|release| release.version.as_str())
+            .collect()
+    }
+
+    // 1.0.0 <- 1.1.0-alpha.1 <- 1.1.0-beta.1 <- 1.1.0, newest first
+    fn beta_cycle(final_tagged: bool, final_commits: Vec<ReleaseCommit>) -> Vec<Release> {
+        vec![
+            release("1.1.0", final_tagged, final_commits),
+            release("1.1.0-beta.1", true, vec![commit("bbbbbbb1", None)]),
+            release("1.1.0-alpha.1", true, vec![commit("aaaaaaa1", None)]),
+            release("1.0.0", true, vec![commit("00000001", None)]),
+        ]
+    }
+
+    #[test]
+    fn keeps_prereleases_apart_by_default() {
+        let releases = beta_cycle(true, vec![commit("ccccccc1", None)]);
+        assert_eq!(
+            roll_up_prereleases(releases.clone(), &PrereleaseMode::Separate),
+            releases
+        );
+    }
+
+    #[test]
+    fn hides_prereleases_in_the_final_release() {
+        let releases = roll_up_prereleases(
+            beta_cycle(true, vec![commit("ccccccc1", None)]),
+            &PrereleaseMode::Hide,
+        );
+        assert_eq!(versions(&releases), vec!["1.1.0", "1.0.0"]);
+        assert_eq!(
+            hashes(&releases[0].commits),
+            vec!["ccccccc1", "bbbbbbb1", "aaaaaaa1"]
+        );
+        assert!(releases[0].prereleases.is_empty());
+    }
+
+    #[test]
+    fn collapses_prereleases_under_the_final_release() {
+        let releases = roll_up_prereleases(
+            beta_cycle(true, vec![commit("ccccccc1", None)]),
+            &PrereleaseMode::Collapse,
+        );
+        assert_eq!(versions(&releases), vec!["1.1.0", "1.0.0"]);
+        assert_eq!(
+            hashes(&releases[0].commits),
+            vec!["ccccccc1", "bbbbbbb1", "aaaaaaa1"]
+        );
+        assert_eq!(
+            versions(&releases[0].prereleases),
+            vec!["1.1.0-beta.1", "1.1.0-alpha.1"]
+        );
+        assert_eq!(
+            hashes(&releases[0].prereleases[0].commits),
+            vec!["bbbbbbb1"]
+        );
+    }
+
+    #[test]
+    fn rolls_up_into_a_pending_release_without_commits() {
+        for mode in [PrereleaseMode::Hide, PrereleaseMode::Collapse] {
+            let releases = roll_up_prereleases(beta_cycle(false, vec![]), &mode);
+            assert_eq!(versions(&releases), vec!["1.1.0", "1.0.0"], "{:?}", mode);
+            assert_eq!(
+                hashes(&releases[0].commits),
+                vec!["bbbbbbb1", "aaaaaaa1"],
+                "{:?}",
+                mode
+            );
+        }
+
+        // Nothing to roll up, the empty pending release is left out
+        let releases = roll_up_prereleases(
+            vec![
+                release("1.1.0", false, vec![]),
+                release("1.0.0", true, vec![commit("00000001", None)]),
+            ],
+            &PrereleaseMode::Hide,
+        );
+        assert_eq!(versions(&releases), vec!["1.0.0"]);
+    }
+
+    #[test]
+    fn rolls_up_commits_once_and_cancels_reverts() {
+        // The alpha commit was picked into the beta as well, and is reverted in the final
+        let mut releases = beta_cycle(true, vec![commit("ccccccc1", Some("aaaaaaa1"))]);
+        releases[1].commits.push(commit("aaaaaaa1", None));
+        let releases = roll_up_prereleases(releases, &PrereleaseMode::Hide);
+        assert_eq!(hashes(&releases[0].commits), vec!["bbbbbbb1"]);
+    }
+
+    #[test]
+    fn keeps_prereleases_without_a_final_release() {
+        let releases = vec![
+            release(UNRELEASED, false, vec![commit("ccccccc1", None)]),
+            release("1.1.0-beta.1", true, vec![commit("bbbbbbb1", None)]),
+            release("1.0.0", true, vec![commit("00000001", None)]),
+        ];
+        assert_eq!(
+            roll_up_prereleases(releases.clone(), &PrereleaseMode::Hide),
+            releases
+        );
+    }
+}
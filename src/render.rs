@@ -0,0 +1,591 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::LazyLock;
+
+use chrono::Utc;
+use clap::ValueEnum;
+use regex::Regex;
+
+use crate::commit::section_title;
+use crate::config::{ChangelogConfig, ChangelogStyle};
+use crate::forge::ForgeLinks;
+use crate::issues::{link_references, markdown_link, split_references, IssueReference};
+use crate::release_notes::{Release, ReleaseCommit, UNRELEASED};
+use crate::tag_format::TagFormat;
+use crate::template::{self, Templates};
+
+#[derive(ValueEnum, Debug, PartialEq, Clone)]
+pub enum ChangelogFormat {
+    Markdown,
+    Json,
+    Yaml,
+    Html,
+}
+
+// Keep a Changelog categories in the order the format lists them
+const KEEPACHANGELOG_CATEGORIES: [&str; 6] = [
+    "Added",
+    "Changed",
+    "Deprecated",
+    "Removed",
+    "Fixed",
+    "Security",
+];
+
+// A Markdown link to the commit in an entry template, e.g. `[{short_hash}]({commit_url})`
+static RE_COMMIT_LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[(?P<text>[^\]]*)\]\(\{commit_url\}\)").unwrap());
+
+pub fn render_json(releases: &[Release]) -> Result<String, Box<dyn Error>> {
+    Ok(serde_json::to_string_pretty(releases)?)
+}
+
+pub fn render_yaml(releases: &[Release]) -> Result<String, Box<dyn Error>> {
+    Ok(serde_yaml::to_string(releases)?)
+}
+
+/// Renders a commit with the entry template, followed by its body when `include_body` is set.
+/// `overrides` replace the variables taken from the commit, e.g. to show the breaking change
+/// explanation in place of the subject.
+fn render_entry(
+    commit: &ReleaseCommit,
+    overrides: &[(&'static str, String)],
+    templates: &Templates,
+    links: Option<&ForgeLinks>,
+    include_body: bool,
+) -> String {
+    let mut variables = HashMap::from([
+        ("hash", commit.hash.clone()),
+        ("short_hash", commit.short_hash.clone()),
+        (
+            "commit_url",
+            links
+                .map(|links| links.commit(&commit.hash))
+                .unwrap_or_default(),
+        ),
+        ("author", commit.author.clone()),
+        (
+            "authors",
+            std::iter::once(&commit.author)
+                .chain(commit.co_authors.iter().map(|co_author| &co_author.name))
+                .cloned()
+                .collect::<Vec<String>>()
+                .join(", "),
+        ),
+        ("email", commit.email.clone()),
+        ("date", commit.date.clone()),
+        ("type", commit.commit_type.clone().unwrap_or_default()),
+        ("scope", commit.scope.clone().unwrap_or_default()),
+        ("subject", commit.subject.clone()),
+        ("body", commit.body.clone().unwrap_or_default()),
+        (
+            "message",
+            commit
+                .message
+                .trim()
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+        ),
+        (
+            "breaking_change",
+            commit.breaking_change.clone().unwrap_or_default(),
+        ),
+    ]);
+    for (name, value) in overrides {
+        variables.insert(name, value.clone());
+    }
+    let mut references = commit.issues.clone();
+    references.extend(commit.pull_request.clone());
+    for name in ["subject", "message", "breaking_change"] {
+        variables.insert(name, link_references(&variables[name], &references));
+    }
+    // Entries are a single line, multi-line overrides are joined into one
+    for name in ["subject", "message"] {
+        variables.insert(
+            name,
+            variables[name]
+                .lines()
+                .map(|line| line.trim())
+                .collect::<Vec<&str>>()
+                .join(" "),
+        );
+    }
+    variables.insert(
+        "issues",
+        commit
+            .issues
+            .iter()
+            .map(markdown_link)
+            .collect::<Vec<String>>()
+            .join(", "),
+    );
+
+    // Without a repository URL there is nothing to link to, only the link text is kept
+    let entry = match links {
+        Some(_) => templates.entry.clone(),
+        None => RE_COMMIT_LINK
+            .replace_all(&templates.entry, "$text")
+            .to_string(),
+    };
+    let mut rendered = template::render(&entry, &variables);
+    if let (true, Some(body)) = (include_body, &commit.body) {
+        rendered.push_str(&render_body(body, &references));
+    }
+    rendered
+}
+
+/// The body as a Markdown block indented under the entry, with the text escaped so that it
+/// reads as it was written.
+fn render_body(body: &str, references: &[IssueReference]) -> String {
+    let mut rendered = String::from("\n");
+    for line in body.lines() {
+        if line.trim().is_empty() {
+            rendered.push('\n');
+            continue;
+        }
+        rendered.push_str("    ");
+        // Only the text around the references is escaped, the links have to stay links
+        for (part, reference) in split_references(line.trim(), references) {
+            match reference {
+                Some(reference) => rendered.push_str(&markdown_link(reference)),
+                None => rendered.push_str(&escape_markdown(part)),
+            }
+        }
+        rendered.push('\n');
+    }
+    rendered.push('\n');
+    rendered
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::new();
+    for (i, c) in text.chars().enumerate() {
+        let is_heading = i == 0 && c == '#';
+        if is_heading || "\\`*_[]<>|".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn render_section_heading(templates: &Templates, title: &str, commit_type: Option<&str>) -> String {
+    template::render(
+        &templates.section_heading,
+        &HashMap::from([
+            ("title", title.to_string()),
+            ("type", commit_type.unwrap_or_default().to_string()),
+        ]),
+    )
+}
+
+fn render_version_heading(
+    release: &Release,
+    changelog_config: &ChangelogConfig,
+    templates: &Templates,
+) -> String {
+    let heading = template::render(
+        &templates.version_heading,
+        &HashMap::from([
+            ("version", release.version.clone()),
+            ("date", release.date.clone()),
+        ]),
+    );
+
+    match changelog_config.style {
+        ChangelogStyle::Default => heading,
+        // Keep a Changelog leaves the date off the Unreleased heading
+        ChangelogStyle::KeepAChangelog if release.version == UNRELEASED => {
+            format!("## [{}]\n\n", UNRELEASED)
+        }
+        ChangelogStyle::KeepAChangelog => format!("{}\n", heading),
+    }
+}
+
+/// The commit type a commit is grouped by. Reverts of commits from earlier releases are
+/// listed with the `revert` commits whatever their own type.
+fn section_type(commit: &ReleaseCommit) -> Option<&str> {
+    match commit.reverts {
+        Some(_) => Some("revert"),
+        None => commit.commit_type.as_deref(),
+    }
+}
+
+fn is_hidden(changelog_config: &ChangelogConfig, commit_type: Option<&str>) -> bool {
+    changelog_config
+        .sections
+        .iter()
+        .any(|section| Some(section.commit_type.as_str()) == commit_type && section.hidden)
+}
+
+pub fn render_markdown_release(
+    release: &Release,
+    changelog_config: &ChangelogConfig,
+    templates: &Templates,
+    links: Option<&ForgeLinks>,
+) -> String {
+    let mut rendered = render_version_heading(release, changelog_config, templates);
+    let sections = match changelog_config.style {
+        ChangelogStyle::Default => render_sections(release, changelog_config, templates, links),
+        ChangelogStyle::KeepAChangelog => {
+            render_categories(release, changelog_config, templates, links)
+        }
+    };
+    rendered.push_str(&sections);
+    if changelog_config.contributors.show {
+        rendered.push_str(&render_contributors(release, changelog_config, templates));
+    }
+    rendered.push_str(&render_prereleases(
+        release,
+        changelog_config,
+        templates,
+        links,
+    ));
+    rendered
+}
+
+/// The pre-releases rolled up into a release, linked to their tags.
+fn render_prereleases(
+    release: &Release,
+    changelog_config: &ChangelogConfig,
+    templates: &Templates,
+    links: Option<&ForgeLinks>,
+) -> String {
+    if release.prereleases.is_empty() {
+        return String::new();
+    }
+
+    let (bullet, separator) = match changelog_config.style {
+        ChangelogStyle::Default => (" - ", ""),
+        ChangelogStyle::KeepAChangelog => ("- ", "\n"),
+    };
+    let mut rendered = render_section_heading(templates, "Pre-releases", None);
+    rendered.push_str(separator);
+    for prerelease in release.prereleases.iter() {
+        rendered.push_str(bullet);
+        match (links, &prerelease.tag) {
+            (Some(links), Some(tag)) => rendered.push_str(&format!(
+                "[{}]({}) - {}",
+                prerelease.version,
+                links.tag(tag),
+                prerelease.date
+            )),
+            _ => rendered.push_str(&format!("{} - {}", prerelease.version, prerelease.date)),
+        }
+        rendered.push('\n');
+    }
+    rendered.push_str(separator);
+    rendered
+}
+
+fn render_contributors(
+    release: &Release,
+    changelog_config: &ChangelogConfig,
+    templates: &Templates,
+) -> String {
+    if release.contributors.is_empty() {
+        return String::new();
+    }
+
+    let (bullet, separator) = match changelog_config.style {
+        ChangelogStyle::Default => (" - ", ""),
+        ChangelogStyle::KeepAChangelog => ("- ", "\n"),
+    };
+    let mut rendered = render_section_heading(templates, "Contributors", None);
+    rendered.push_str(separator);
+    for contributor in release.contributors.iter() {
+        rendered.push_str(bullet);
+        rendered.push_str(&contributor.name);
+        if contributor.new {
+            rendered.push_str(" (new contributor)");
+        }
+        rendered.push('\n');
+    }
+    rendered.push_str(separator);
+    rendered
+}
+
+/// Commits listed under one heading of a release.
+pub struct CommitGroup<'a> {
+    pub title: String,
+    pub commit_type: Option<&'a str>,
+    pub commits: Vec<&'a ReleaseCommit>,
+}
+
+/// The headings of a release with the commits under each, in the order the changelog style
+/// lists them. Breaking changes are not singled out.
+pub fn group_commits<'a>(
+    release: &'a Release,
+    changelog_config: &'a ChangelogConfig,
+) -> Vec<CommitGroup<'a>> {
+    match changelog_config.style {
+        ChangelogStyle::Default => section_groups(release, changelog_config),
+        ChangelogStyle::KeepAChangelog => category_groups(release, changelog_config),
+    }
+}
+
+fn section_groups<'a>(
+    release: &'a Release,
+    changelog_config: &'a ChangelogConfig,
+) -> Vec<CommitGroup<'a>> {
+    // Configured sections come first in their configured order, followed by any other commit
+    // types alphabetically and finally the commits that do not follow the convention
+    let mut ordered_sections: Vec<(Option<&str>, String)> = vec![];
+    for section in changelog_config.sections.iter() {
+        if !section.hidden {
+            ordered_sections.push((Some(&section.commit_type), section.title.clone()));
+        }
+    }
+    let mut other_types: Vec<&str> = release
+        .commits
+        .iter()
+        .filter_map(section_type)
+        .filter(|commit_type| {
+            !changelog_config
+                .sections
+                .iter()
+                .any(|section| &section.commit_type == commit_type)
+        })
+        .collect();
+    other_types.sort();
+    other_types.dedup();
+    for commit_type in other_types {
+        ordered_sections.push((Some(commit_type), section_title(Some(commit_type))));
+    }
+    ordered_sections.push((None, section_title(None)));
+
+    ordered_sections
+        .into_iter()
+        .map(|(commit_type, title)| CommitGroup {
+            title,
+            commit_type,
+            commits: release
+                .commits
+                .iter()
+                .filter(|commit| section_type(commit) == commit_type)
+                .collect(),
+        })
+        .filter(|group| !group.commits.is_empty())
+        .collect()
+}
+
+fn category_groups<'a>(
+    release: &'a Release,
+    changelog_config: &'a ChangelogConfig,
+) -> Vec<CommitGroup<'a>> {
+    let mut categories: Vec<(String, Vec<&ReleaseCommit>)> = KEEPACHANGELOG_CATEGORIES
+        .iter()
+        .map(|category| (category.to_string(), vec![]))
+        .collect();
+    for commit in release.commits.iter() {
+        if is_hidden(changelog_config, section_type(commit)) {
+            continue;
+        }
+        let category = changelog_config.category(section_type(commit));
+        match categories.iter_mut().find(|(name, _)| name == &category) {
+            Some((_, commits)) => commits.push(commit),
+            None => categories.push((category, vec![commit])),
+        }
+    }
+    // Custom categories follow the standard ones alphabetically
+    categories[KEEPACHANGELOG_CATEGORIES.len()..].sort_by(|a, b| a.0.cmp(&b.0));
+
+    categories
+        .into_iter()
+        .filter(|(_, commits)| !commits.is_empty())
+        .map(|(title, commits)| CommitGroup {
+            title,
+            commit_type: None,
+            commits,
+        })
+        .collect()
+}
+
+fn render_sections(
+    release: &Release,
+    changelog_config: &ChangelogConfig,
+    templates: &Templates,
+    links: Option<&ForgeLinks>,
+) -> String {
+    let mut rendered = String::new();
+
+    // Breaking changes go first so that people upgrading see them before anything else
+    let breaking_commits: Vec<&ReleaseCommit> = release
+        .commits
+        .iter()
+        .filter(|commit| commit.breaking)
+        .collect();
+    if !breaking_commits.is_empty() {
+        rendered.push_str(&render_section_heading(templates, "BREAKING CHANGES", None));
+        for commit in breaking_commits {
+            // Entry templates may show either the subject or the message
+            let explanation = commit.breaking_change.as_ref().unwrap_or(&commit.subject);
+            rendered.push_str(&render_entry(
+                commit,
+                &[
+                    ("message", explanation.clone()),
+                    ("subject", explanation.clone()),
+                ],
+                templates,
+                links,
+                false,
+            ));
+        }
+    }
+
+    for group in section_groups(release, changelog_config) {
+        rendered.push_str(&render_section_heading(
+            templates,
+            &group.title,
+            group.commit_type,
+        ));
+        for commit in group.commits {
+            rendered.push_str(&render_entry(
+                commit,
+                &[],
+                templates,
+                links,
+                changelog_config.include_body,
+            ));
+        }
+    }
+    rendered
+}
+
+fn render_categories(
+    release: &Release,
+    changelog_config: &ChangelogConfig,
+    templates: &Templates,
+    links: Option<&ForgeLinks>,
+) -> String {
+    let mut rendered = String::new();
+
+    for group in category_groups(release, changelog_config) {
+        rendered.push_str(&render_section_heading(templates, &group.title, None));
+        rendered.push('\n');
+        for commit in group.commits {
+            let overrides = if commit.breaking {
+                vec![("subject", format!("**BREAKING:** {}", commit.subject))]
+            } else {
+                vec![]
+            };
+            rendered.push_str(&render_entry(
+                commit,
+                &overrides,
+                templates,
+                links,
+                changelog_config.include_body,
+            ));
+            // There is no breaking changes section in this style, the explanation goes under
+            // the entry instead
+            if let Some(breaking_change) = &commit.breaking_change {
+                let mut references = commit.issues.clone();
+                references.extend(commit.pull_request.clone());
+                rendered.push_str(&render_body(breaking_change, &references));
+            }
+        }
+        rendered.push('\n');
+    }
+    rendered
+}
+
+/// Keep a Changelog always starts with an Unreleased section, even an empty one.
+pub fn with_unreleased(releases: &[Release]) -> Vec<Release> {
+    let mut releases = releases.to_vec();
+    if releases.first().map(|release| release.version.as_str()) != Some(UNRELEASED) {
+        releases.insert(
+            0,
+            Release {
+                version: String::from(UNRELEASED),
+                date: Utc::now().date_naive().to_string(),
+                tag: None,
+                commits: vec![],
+                contributors: vec![],
+                prereleases: vec![],
+            },
+        );
+    }
+    releases
+}
+
+/// Link to the changes of each release: its compare view against the release listed after
+/// it, or its tag for the oldest one.
+pub fn release_links(
+    releases: &[Release],
+    links: &ForgeLinks,
+    tag_format: &TagFormat,
+) -> Vec<String> {
+    // The pending release is compared against the ref it is about to be tagged with
+    let refs: Vec<String> = releases
+        .iter()
+        .map(|release| match (&release.tag, release.version.as_str()) {
+            (Some(tag), _) => tag.clone(),
+            (None, UNRELEASED) => String::from("HEAD"),
+            (None, version) => tag_format.tag(version),
+        })
+        .collect();
+
+    refs.iter()
+        .enumerate()
+        .map(|(i, release_ref)| match refs.get(i + 1) {
+            Some(previous) => links.compare(previous, release_ref),
+            None => links.tag(release_ref),
+        })
+        .collect()
+}
+
+/// Link reference definitions pointing each version heading at its compare view.
+pub fn render_link_definitions(
+    releases: &[Release],
+    links: Option<&ForgeLinks>,
+    tag_format: &TagFormat,
+) -> String {
+    let links = match links {
+        Some(links) => links,
+        None => return String::new(),
+    };
+
+    let mut rendered = String::new();
+    for (release, link) in releases
+        .iter()
+        .zip(release_links(releases, links, tag_format))
+    {
+        rendered.push_str(&format!("[{}]: {}\n", release.version, link));
+    }
+    rendered
+}
+
+pub fn render_markdown(
+    releases: &[Release],
+    changelog_config: &ChangelogConfig,
+    templates: &Templates,
+    links: Option<&ForgeLinks>,
+) -> String {
+    let releases = match changelog_config.style {
+        ChangelogStyle::Default => releases.to_vec(),
+        ChangelogStyle::KeepAChangelog => with_unreleased(releases),
+    };
+
+    let mut rendered = match &templates.header {
+        Some(header) => format!("{}\n", template::render(header, &HashMap::new())),
+        None => String::new(),
+    };
+    for release in releases.iter() {
+        rendered.push_str(&render_markdown_release(
+            release,
+            changelog_config,
+            templates,
+            links,
+        ));
+    }
+    if changelog_config.style == ChangelogStyle::KeepAChangelog {
+        rendered.push_str(&render_link_definitions(
+            &releases,
+            links,
+            &TagFormat::from_config(changelog_config),
+        ));
+    }
+    rendered
+}
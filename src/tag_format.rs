@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use regex::Regex;
+
+use crate::config::ChangelogConfig;
+use crate::template;
+
+pub const DEFAULT_TAG_FORMAT: &str = "{version}";
+
+/// How release tags are named, e.g. `v{version}` or `{package}@{version}`.
+#[derive(Debug, Clone)]
+pub struct TagFormat {
+    format: String,
+    package: String,
+    re_tag: Option<Regex>,
+}
+
+impl TagFormat {
+    pub fn from_config(changelog_config: &ChangelogConfig) -> TagFormat {
+        let format = changelog_config
+            .tag_format
+            .clone()
+            .unwrap_or(String::from(DEFAULT_TAG_FORMAT));
+        let package = changelog_config.package.clone().unwrap_or_default();
+        TagFormat {
+            re_tag: tag_regex(&format, &package),
+            format,
+            package,
+        }
+    }
+
+    /// The tag name for `version`.
+    pub fn tag(&self, version: &str) -> String {
+        template::render(
+            &self.format,
+            &HashMap::from([
+                ("version", version.to_string()),
+                ("package", self.package.clone()),
+            ]),
+        )
+        .trim_end()
+        .to_string()
+    }
+
+    /// The version a tag stands for, or `None` for tags that do not follow the format.
+    pub fn version(&self, tag: &str) -> Option<String> {
+        self.re_tag
+            .as_ref()?
+            .captures(tag)
+            .map(|caps| caps["version"].to_string())
+    }
+}
+
+// Matches the tags rendered from `format` and captures their `version`
+fn tag_regex(format: &str, package: &str) -> Option<Regex> {
+    let mut pattern = String::from("^");
+    let mut last_end = 0;
+    for caps in template::RE_PLACEHOLDER.captures_iter(format) {
+        let placeholder = caps.get(0).unwrap();
+        pattern.push_str(&regex::escape(&format[last_end..placeholder.start()]));
+        last_end = placeholder.end();
+        match &caps["name"] {
+            "version" => pattern.push_str(r"(?P<version>\d+\.\d+\.\d+\S*)"),
+            "package" => pattern.push_str(&regex::escape(package)),
+            _ => pattern.push_str(r"\S*?"),
+        }
+    }
+    pattern.push_str(&regex::escape(&format[last_end..]));
+    pattern.push('$');
+
+    Regex::new(&pattern).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag_format(format: &str, package: Option<&str>) -> TagFormat {
+        TagFormat::from_config(&ChangelogConfig {
+            tag_format: Some(format.to_string()),
+            package: package.map(|package| package.to_string()),
+            ..ChangelogConfig::default()
+        })
+    }
+
+    #[test]
+    fn reads_versions_from_tags() {
+        let cases = [
+            ("{version}", None, "1.2.3", Some("1.2.3")),
+            ("{version}", None, "1.2.3-beta.1", Some("1.2.3-beta.1")),
+            ("{version}", None, "deploy-prod", None),
+            ("v{version}", None, "v1.2.3", Some("1.2.3")),
+            ("v{version}", None, "1.2.3", None),
+            (
+                "{package}@{version}",
+                Some("api"),
+                "api@2.0.0",
+                Some("2.0.0"),
+            ),
+            ("{package}@{version}", Some("api"), "web@2.0.0", None),
+        ];
+        for (format, package, tag, version) in cases {
+            assert_eq!(
+                tag_format(format, package).version(tag).as_deref(),
+                version,
+                "{} {}",
+                format,
+                tag
+            );
+        }
+    }
+
+    #[test]
+    fn names_tags() {
+        assert_eq!(tag_format("v{version}", None).tag("1.2.3"), "v1.2.3");
+        assert_eq!(
+            tag_format("{package}@{version}", Some("api")).tag("1.2.3"),
+            "api@1.2.3"
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::config::{ChangelogStyle, TemplatesConfig};
+
+pub const DEFAULT_VERSION_HEADING: &str = "## {version} - {date}";
+pub const DEFAULT_SECTION_HEADING: &str = "### {title}";
+pub const DEFAULT_ENTRY: &str = " - [{short_hash}]({commit_url}) ({authors}) {message}";
+
+pub const KEEPACHANGELOG_HEADER: &str = "# Changelog
+
+All notable changes to this project will be documented in this file.
+
+The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.1.0/),
+and this project adheres to [Semantic Versioning](https://semver.org/spec/v2.0.0.html).
+";
+pub const KEEPACHANGELOG_VERSION_HEADING: &str = "## [{version}] - {date}";
+pub const KEEPACHANGELOG_ENTRY: &str = "- {subject} ([{short_hash}]({commit_url}))";
+
+// Versions always start with a digit, which keeps entry lines from being taken for headings
+const VERSION_PATTERN: &str = r"(?P<version>\d[^\s\]\)]*|Unreleased)";
+
+pub static RE_PLACEHOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{(?P<name>[a-z_]+)\}").unwrap());
+
+/// The changelog templates with inline and file based settings resolved.
+#[derive(Debug, Clone)]
+pub struct Templates {
+    pub header: Option<String>,
+    pub version_heading: String,
+    pub section_heading: String,
+    pub entry: String,
+}
+
+impl Templates {
+    /// Resolves the configured templates, falling back to the defaults of the changelog style.
+    pub fn load(
+        config: &TemplatesConfig,
+        style: &ChangelogStyle,
+    ) -> Result<Templates, Box<dyn Error>> {
+        let (header, version_heading, entry) = match style {
+            ChangelogStyle::Default => (None, DEFAULT_VERSION_HEADING, DEFAULT_ENTRY),
+            ChangelogStyle::KeepAChangelog => (
+                Some(String::from(KEEPACHANGELOG_HEADER)),
+                KEEPACHANGELOG_VERSION_HEADING,
+                KEEPACHANGELOG_ENTRY,
+            ),
+        };
+
+        Ok(Templates {
+            header: resolve(&config.header, &config.header_file)?.or(header),
+            version_heading: resolve(&config.version_heading, &config.version_heading_file)?
+                .unwrap_or(String::from(version_heading)),
+            section_heading: resolve(&config.section_heading, &config.section_heading_file)?
+                .unwrap_or(String::from(DEFAULT_SECTION_HEADING)),
+            entry: resolve(&config.entry, &config.entry_file)?.unwrap_or(String::from(entry)),
+        })
+    }
+}
+
+fn resolve(
+    inline: &Option<String>,
+    file_name: &Option<String>,
+) -> Result<Option<String>, Box<dyn Error>> {
+    match (inline, file_name) {
+        (Some(template), _) => Ok(Some(template.clone())),
+        (None, Some(file_name)) => match fs::read_to_string(file_name) {
+            Ok(template) => Ok(Some(template)),
+            Err(e) => Err(format!("Unable to read template {}: {}", file_name, e).into()),
+        },
+        (None, None) => Ok(None),
+    }
+}
+
+/// Replaces every `{name}` in the template with its value and terminates the result with a
+/// newline. Placeholders without a value are left as they are.
+pub fn render(template: &str, variables: &HashMap<&str, String>) -> String {
+    let mut rendered = RE_PLACEHOLDER
+        .replace_all(
+            template.trim_end_matches('\n'),
+            |caps: &regex::Captures| match variables.get(&caps["name"]) {
+                Some(value) => value.clone(),
+                None => caps[0].to_string(),
+            },
+        )
+        .to_string();
+    rendered.push('\n');
+    rendered
+}
+
+/// Builds a regex that recognizes lines rendered from a version heading template and captures
+/// the `version` placeholder.
+pub fn heading_regex(template: &str) -> Regex {
+    let first_line = template
+        .trim_start_matches('\n')
+        .lines()
+        .next()
+        .unwrap_or("");
+
+    // Only the text up to the `{version}` placeholder is needed to recognize a heading
+    let mut pattern = String::from("(?m)^");
+    let mut last_end = 0;
+    for caps in RE_PLACEHOLDER.captures_iter(first_line) {
+        let placeholder = caps.get(0).unwrap();
+        pattern.push_str(&regex::escape(&first_line[last_end..placeholder.start()]));
+        last_end = placeholder.end();
+        if &caps["name"] == "version" {
+            pattern.push_str(VERSION_PATTERN);
+            return Regex::new(&pattern).unwrap();
+        }
+        pattern.push_str(".*?");
+    }
+
+    // Without a `{version}` placeholder the version is expected right after the literal text
+    pattern.push_str(&regex::escape(&first_line[last_end..]));
+    pattern.push_str(VERSION_PATTERN);
+    Regex::new(&pattern).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_version_headings() {
+        let cases = [
+            (
+                DEFAULT_VERSION_HEADING,
+                "## 1.2.0 - 2024-01-01",
+                Some("1.2.0"),
+            ),
+            (
+                DEFAULT_VERSION_HEADING,
+                "## Unreleased - 2024-01-01",
+                Some("Unreleased"),
+            ),
+            (DEFAULT_VERSION_HEADING, "## Notes", None),
+            (DEFAULT_VERSION_HEADING, " - [1a2b3c4](url) 1.2.0", None),
+            (
+                KEEPACHANGELOG_VERSION_HEADING,
+                "## [1.2.0-beta.1] - 2024-01-01",
+                Some("1.2.0-beta.1"),
+            ),
+            (
+                KEEPACHANGELOG_VERSION_HEADING,
+                "## [Unreleased]",
+                Some("Unreleased"),
+            ),
+            (
+                "## {date} - v{version}",
+                "## 2024-01-01 - v1.2.0",
+                Some("1.2.0"),
+            ),
+            ("\n# Release v", "# Release v1.2.0", Some("1.2.0")),
+            ("## v{version}", "## 1.2.0 - 2024-01-01", None),
+        ];
+        for (template, line, version) in cases {
+            let found = heading_regex(template)
+                .captures(line)
+                .map(|caps| caps["version"].to_string());
+            assert_eq!(found.as_deref(), version, "{} {}", template, line);
+        }
+    }
+}
@@ -1,4 +1,6 @@
-use crate::{references::update_references, release_notes};
+use crate::{
+    config::ChangelogConfig, references::update_references, release_notes, tag_format::TagFormat,
+};
 use ginger_shared_rs::{OutputType, ReleaserConfig, Version};
 use inquire::{
     ui::{Color, RenderConfig, Styled},
@@ -67,16 +69,23 @@ pub fn update_json(
     Ok(contents.to_string())
 }
 
-fn description_render_config() -> RenderConfig {
+pub fn description_render_config() -> RenderConfig {
     RenderConfig::default()
         .with_canceled_prompt_indicator(Styled::new("<skipped>").with_fg(Color::DarkYellow))
 }
 
-pub fn update_project_source(config: &ReleaserConfig, require_long_msg: bool) {
+pub fn update_project_source(
+    config: &ReleaserConfig,
+    changelog_config: &ChangelogConfig,
+    require_long_msg: bool,
+    rebuild_changelog: bool,
+) {
     update_references(&config);
     match release_notes::generate_release_notes(
-        &config.settings.git_url_prefix.clone().unwrap(),
+        &config.settings.git_url_prefix.clone().unwrap_or_default(),
         config.version,
+        changelog_config,
+        rebuild_changelog,
     ) {
         Err(e) => {
             println!("Unable to generate {:?}", e);
@@ -134,7 +143,8 @@ pub fn update_project_source(config: &ReleaserConfig, require_long_msg: bool) {
             }
 
             // Create a tag with the version
-            let tag_name = config.version.formatted();
+            let tag_name =
+                TagFormat::from_config(changelog_config).tag(&config.version.formatted());
             let status = Command::new("git")
                 .arg("tag")
                 .arg(&tag_name)